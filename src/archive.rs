@@ -0,0 +1,59 @@
+use crate::process::Timespan;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Ledger of processed videos. Each line holds a video ID and a timespan.
+#[derive(Debug)]
+pub struct Archive {
+    path: PathBuf,
+    entries: RefCell<HashSet<String>>,
+}
+
+impl Archive {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(s) => s
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            path,
+            entries: RefCell::new(entries),
+        })
+    }
+
+    pub fn contains(&self, id: &str, timespan: &Timespan) -> bool {
+        self.entries.borrow().contains(&key(id, timespan))
+    }
+
+    pub fn record(&self, id: &str, timespan: &Timespan) -> io::Result<()> {
+        let key = key(id, timespan);
+        if self.entries.borrow().contains(&key) {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", key)?;
+        self.entries.borrow_mut().insert(key);
+        Ok(())
+    }
+}
+
+fn key(id: &str, timespan: &Timespan) -> String {
+    format!("{} {}", id, timespan)
+}
@@ -0,0 +1,153 @@
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    str::FromStr,
+};
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl FromStr for Compression {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "zstd" | "zst" => Ok(Self::Zstd),
+            _ => Err("invalid compression"),
+        }
+    }
+}
+
+impl Compression {
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    /// Detects compression from the extension of the file name: comments.xml.gz
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Writer which compresses data if needed. `finish` must be called to complete the stream.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W, compression: Option<Compression>) -> io::Result<Self> {
+        Ok(match compression {
+            None => Self::Plain(writer),
+            Some(Compression::Gzip) => {
+                Self::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Some(Compression::Zstd) => Self::Zstd(zstd::Encoder::new(writer, 0)?),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            Self::Plain(w) => w,
+            Self::Gzip(w) => w.finish()?,
+            Self::Zstd(w) => w.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(w) => w.write(buf),
+            Self::Gzip(w) => w.write(buf),
+            Self::Zstd(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(w) => w.flush(),
+            Self::Gzip(w) => w.flush(),
+            Self::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+/// Opens a file, decompressing it if it starts with the magic number of gzip or zstd.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn BufRead>> {
+    decode(BufReader::new(File::open(path)?))
+}
+
+fn decode<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn BufRead>> {
+    let head = reader.fill_buf()?;
+    Ok(if head.starts_with(GZIP_MAGIC) {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if head.starts_with(ZSTD_MAGIC) {
+        Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        Box::new(reader)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    fn roundtrip(compression: Option<Compression>) -> (Vec<u8>, String) {
+        let mut encoder = Encoder::new(vec![], compression).unwrap();
+        encoder.write_all(b"<packet></packet>").unwrap();
+        let buf = encoder.finish().unwrap();
+
+        let mut res = String::new();
+        decode(Cursor::new(buf.clone()))
+            .unwrap()
+            .read_to_string(&mut res)
+            .unwrap();
+        (buf, res)
+    }
+
+    #[test]
+    fn test_compression() {
+        let (buf, res) = roundtrip(None);
+        assert_eq!(buf, b"<packet></packet>");
+        assert_eq!(res, "<packet></packet>");
+
+        let (buf, res) = roundtrip(Some(Compression::Gzip));
+        assert!(buf.starts_with(GZIP_MAGIC));
+        assert_eq!(res, "<packet></packet>");
+
+        let (buf, res) = roundtrip(Some(Compression::Zstd));
+        assert!(buf.starts_with(ZSTD_MAGIC));
+        assert_eq!(res, "<packet></packet>");
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Compression::from_path("a.xml.gz"), Some(Compression::Gzip));
+        assert_eq!(
+            Compression::from_path("a.json.zst"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_path("a.xml"), None);
+    }
+}
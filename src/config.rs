@@ -13,6 +13,6 @@ impl Config {
     }
 
     pub fn save(&self) -> Result<(), confy::ConfyError> {
-        confy::store(APP_NAME, &self)
+        confy::store(APP_NAME, self)
     }
 }
@@ -0,0 +1,68 @@
+use crate::{
+    compress::{self, Compression, Encoder},
+    error::{Error, Result},
+    process::{Format, FormatOptions},
+};
+use clap::Parser;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+#[derive(Debug, Parser)]
+pub struct Opts {
+    /// Output format [default: extension of the output file]
+    #[clap(short, long)]
+    format: Option<Format>,
+    #[clap(flatten)]
+    format_options: FormatOptions,
+    /// Compress the output: gzip, zstd [default: extension of the output file]
+    #[clap(long)]
+    compress: Option<Compression>,
+    /// Comment file to convert (XML or JSON, optionally compressed)
+    input: String,
+    /// Output file path, or "-" to write to stdout
+    output: String,
+}
+
+pub fn convert(opts: &Opts) -> Result<()> {
+    opts.format_options.ass().validate()?;
+    opts.format_options.subtitle().validate()?;
+    let output = Path::new(&opts.output);
+    let compression = opts.compress.or_else(|| Compression::from_path(output));
+    let format = match opts.format.as_ref() {
+        Some(f) => f.clone(),
+        // comments.xml.gz
+        None => match Compression::from_path(output) {
+            Some(_) => Path::new(output.file_stem().unwrap_or_default()),
+            None => output,
+        }
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| e.to_lowercase().parse().ok())
+        .ok_or(Error::UnknownFormat)?,
+    };
+
+    let comments = nicodo::read_comments(compress::open(&opts.input)?)?;
+    let writer = BufWriter::new(if opts.output == "-" {
+        Box::new(std::io::stdout()) as Box<dyn Write>
+    } else {
+        Box::new(File::create(&opts.output)?)
+    });
+    let mut writer = Encoder::new(writer, compression)?;
+    format.write(&mut writer, &comments, &opts.format_options, None)?;
+    writer.finish()?;
+
+    eprintln!(
+        "Writing {} comments to {}",
+        comments.len(),
+        if opts.output == "-" {
+            "stdout".to_string()
+        } else {
+            format!("\"{}\"", &opts.output)
+        }
+    );
+
+    Ok(())
+}
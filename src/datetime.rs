@@ -1,4 +1,4 @@
-use chrono::{Duration as RawDuration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{Duration as RawDuration, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::{
@@ -6,6 +6,7 @@ use std::{
     str::FromStr,
 };
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone)]
 pub enum DateTime {
     Posted,
@@ -23,6 +24,53 @@ impl DateTime {
             Self::DateTime(d) => *d,
         }
     }
+
+    /// Same as `datetime`, but "latest" is the current time in JST to compare with posting dates
+    pub fn datetime_in_jst(&self, posted_date_time: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Self::Latest => jst_now(),
+            _ => self.datetime(posted_date_time),
+        }
+    }
+
+    /// Returns whether the date time is known to be after `other` before the posted date is
+    /// known, that is both are relative to it or both are absolute
+    pub fn is_after(&self, other: &Self) -> bool {
+        let offset = |d: &Self| match d {
+            Self::Posted => Some(RawDuration::zero()),
+            Self::PostedPlus(d) => Some(d.duration()),
+            _ => None,
+        };
+        match (self, other) {
+            (Self::DateTime(a), Self::DateTime(b)) => a > b,
+            _ => matches!((offset(self), offset(other)), (Some(a), Some(b)) if a > b),
+        }
+    }
+
+    /// Returns the date time in JST unless it is relative to the posted date
+    pub fn absolute(&self) -> Option<NaiveDateTime> {
+        match self {
+            Self::Posted | Self::PostedPlus(_) => None,
+            Self::Latest => Some(jst_now()),
+            Self::DateTime(d) => Some(*d),
+        }
+    }
+}
+
+/// Current time in JST, in which dates on niconico are given
+fn jst_now() -> NaiveDateTime {
+    Utc::now().with_timezone(&nicodo::jst()).naive_local()
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Posted => write!(f, "posted"),
+            Self::PostedPlus(d) => write!(f, "posted+{}", d),
+            Self::Latest => write!(f, "latest"),
+            Self::DateTime(d) => write!(f, "{}", d.format("%Y-%m-%d %H:%M:%S")),
+        }
+    }
 }
 
 impl FromStr for DateTime {
@@ -96,6 +144,40 @@ impl FromStr for Duration {
     }
 }
 
+/// Playback time: 90, 01:30, 1:02:03.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaybackTime(isize);
+
+impl PlaybackTime {
+    /// Returns the time in centiseconds, the unit of vpos
+    pub fn vpos(&self) -> isize {
+        self.0
+    }
+}
+
+impl FromStr for PlaybackTime {
+    type Err = &'static str;
+
+    fn from_str(t: &str) -> Result<PlaybackTime, &'static str> {
+        const ERR: &str = "invalid playback time";
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"^(?:(?:(\d+):)?(\d+):)?(\d+(?:\.\d+)?)$").unwrap();
+        }
+        let c = RE.captures(t).ok_or(ERR)?;
+        let part = |i: usize| c.get(i).map_or(Ok(0), |m| m.as_str().parse::<isize>());
+        let (h, m) = (part(1).map_err(|_| ERR)?, part(2).map_err(|_| ERR)?);
+        let cs = (c[3].parse::<f64>().map_err(|_| ERR)? * 100.0).round();
+        if cs > isize::MAX as f64 {
+            return Err(ERR);
+        }
+        h.checked_mul(3600)
+            .and_then(|h| h.checked_add(m.checked_mul(60)?))
+            .and_then(|s| s.checked_mul(100)?.checked_add(cs as isize))
+            .map(PlaybackTime)
+            .ok_or(ERR)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -126,6 +208,29 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_is_after() {
+        let d = |s: &str| s.parse::<DateTime>().unwrap();
+        assert!(d("posted+1w").is_after(&d("posted")));
+        assert!(!d("posted").is_after(&d("posted+1w")));
+        assert!(!d("posted+1d").is_after(&d("posted+1d")));
+        assert!(d("2019-01-02").is_after(&d("2019-01-01")));
+        assert!(!d("2019-01-01").is_after(&d("2019-01-02")));
+        assert!(!d("2019-01-01").is_after(&d("posted")));
+        assert!(!d("latest").is_after(&d("2019-01-01")));
+    }
+
+    #[test]
+    fn test_datetime_display() {
+        for s in &["posted", "posted+1w", "latest", "2010-01-01 10:01:02"] {
+            assert_eq!(s.parse::<DateTime>().unwrap().to_string(), *s);
+        }
+        assert_eq!(
+            "2010-01-01".parse::<DateTime>().unwrap().to_string(),
+            "2010-01-01 00:00:00"
+        );
+    }
+
     #[test]
     fn test_duration() {
         let d = "1h".parse::<Duration>().unwrap();
@@ -141,4 +246,16 @@ mod test {
         let d = "7w".parse::<Duration>().unwrap();
         assert_eq!(d.duration(), RawDuration::days(49));
     }
+
+    #[test]
+    fn test_playback_time() {
+        let t = |s: &str| s.parse::<PlaybackTime>().map(|t| t.vpos());
+        assert_eq!(t("90"), Ok(9000));
+        assert_eq!(t("01:30"), Ok(9000));
+        assert_eq!(t("1:02:03.5"), Ok(372350));
+        assert!(t("1:2:3:4").is_err());
+        assert!(t("-1").is_err());
+        assert!(t("9223372036854775807:00:00").is_err());
+        assert!(t(&"9".repeat(400)).is_err());
+    }
 }
@@ -1,6 +1,7 @@
 use derive_more::From;
 use thiserror::Error;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error, From)]
 pub enum Error {
     #[error("{0}")]
@@ -9,9 +10,43 @@ pub enum Error {
     Config(confy::ConfyError),
     #[error("start, end, or interval is missing")]
     Period,
+    #[error("start is after end")]
+    PeriodOrder,
+    #[error("interval must be positive")]
+    Interval,
+    #[error("format cannot be determined from the output file name")]
+    UnknownFormat,
+    #[error("files in the format cannot be read")]
+    UnreadableFormat,
+    #[cfg(any(feature = "sqlite", feature = "parquet"))]
+    #[error("comments cannot be converted into the format")]
+    UnwritableFormat,
+    #[error("the JSON envelope needs the video and wayback, which a comment file does not hold")]
+    Envelope,
+    #[cfg(feature = "sqlite")]
+    #[error("the format cannot be written to stdout")]
+    StdoutFormat,
+    #[error("only JSONL can hold comments of more than one video in stdout")]
+    StdoutVideos,
+    #[error("comments in stdout cannot be updated")]
+    StdoutUpdate,
+    #[error("files in the format cannot be read back to be updated")]
+    UpdateFormat,
+    #[error("file not found: {0}")]
+    FileNotFound(String),
+    #[error("statistics can be shown only for a video or a comment file")]
+    StatsInput,
+    #[error("bucket size must be positive")]
+    Bucket,
+    #[error("dates of the search cannot be relative to the posted date")]
+    SearchDate,
+    #[error("the output template uses a value which is unknown")]
+    Template,
     #[error("{0}")]
     IO(std::io::Error),
     #[error("{0}")]
+    Json(serde_json::Error),
+    #[error("{0}")]
     Error(Box<dyn std::error::Error + Send>),
 }
 
@@ -6,6 +6,7 @@ use std::str::FromStr;
 pub enum Id {
     Video(String),
     Channel(String),
+    Series(String),
 }
 
 impl FromStr for Id {
@@ -14,7 +15,8 @@ impl FromStr for Id {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         lazy_static! {
             static ref RE: Regex = Regex::new(r"https://ch.nicovideo.jp/(.+?)(?:\?|$)").unwrap();
-            static ref RE2: Regex = Regex::new(r"https://www.nicovideo.jp/series/(.+?)(?:\?|$)").unwrap();
+            static ref RE2: Regex =
+                Regex::new(r"https://www.nicovideo.jp/series/(.+?)(?:\?|$)").unwrap();
         }
 
         if let Some(c) = RE.captures(s).and_then(|c| c.get(1)) {
@@ -22,12 +24,12 @@ impl FromStr for Id {
         }
 
         if let Some(c) = RE2.captures(s).and_then(|c| c.get(1)) {
-            return Ok(Self::Channel(c.as_str().to_string()));
+            return Ok(Self::Series(c.as_str().to_string()));
         }
 
-        return Ok(Self::Video(
+        Ok(Self::Video(
             s.replace("https://www.nicovideo.jp/watch/", ""),
-        ));
+        ))
     }
 }
 
@@ -44,10 +46,12 @@ fn test_id_parse() {
     );
     assert_eq!(
         "https://www.nicovideo.jp/series/zzz".parse::<Id>().unwrap(),
-        Id::Channel("zzz".to_string())
+        Id::Series("zzz".to_string())
     );
     assert_eq!(
-        "https://www.nicovideo.jp/series/zzz?aaa".parse::<Id>().unwrap(),
-        Id::Channel("zzz".to_string())
+        "https://www.nicovideo.jp/series/zzz?aaa"
+            .parse::<Id>()
+            .unwrap(),
+        Id::Series("zzz".to_string())
     );
 }
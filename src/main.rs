@@ -1,13 +1,19 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dialoguer::Input;
 use error::{Error, Result};
 use std::process::exit;
 
+mod archive;
+mod compress;
 mod config;
+mod convert;
 mod datetime;
 mod error;
 mod id;
 mod process;
+mod report;
+mod stats;
+mod template;
 
 #[tokio::main]
 async fn main() {
@@ -30,7 +36,9 @@ struct Opts {
     /// Format
     #[clap(short, long, default_value = "xml")]
     format: process::Format,
-    /// Output directory path
+    #[clap(flatten)]
+    format_options: process::FormatOptions,
+    /// Output directory path, or "-" to write to stdout
     #[clap(short, long, default_value = ".")]
     output: String,
     /// Date: 2019-01-01, 2019-01-01 12:00:00
@@ -63,8 +71,79 @@ struct Opts {
     /// Delay (seconds)
     #[clap(long, default_value = "1")]
     delay: u64,
-    /// Video ID, video URL, or channel URL
+    /// Write a JSON report of the run to the file
+    #[clap(long)]
+    report: Option<String>,
+    /// Skip videos whose output file already exists. Single video IDs and timespans relative to
+    /// the posted date or "latest" need the video info to name the file.
+    #[clap(long)]
+    skip_existing: bool,
+    /// Merge new comments into the existing output file (XML, JSON, JSONL or SQLite)
+    #[clap(long, conflicts_with = "skip-existing")]
+    update: bool,
+    /// Skip videos recorded in the file, and record processed videos in it, including those
+    /// without comments to write
+    #[clap(long)]
+    download_archive: Option<String>,
+    /// Output file path relative to the output directory: {channel}/{title}_{start}.{ext}
+    /// Placeholders: id, title, channel, series, episode, registered_at, start, end, interval, ext
+    #[clap(long)]
+    output_template: Option<template::Template>,
+    /// Drop comments matching NG rules in the file: a word, "regex:<pattern>" or "user:<user id>"
+    /// per line
+    #[clap(long)]
+    ng_file: Option<String>,
+    /// Keep comments at or after the playback time: 90, 01:30, 1:02:03.5
+    #[clap(long)]
+    from: Option<datetime::PlaybackTime>,
+    /// Keep comments before the playback time
+    #[clap(long)]
+    to: Option<datetime::PlaybackTime>,
+    /// Keep comments posted at or after the date (JST): 2019-01-01, 2019-01-01 12:00:00, posted+1d
+    #[clap(long)]
+    since: Option<datetime::DateTime>,
+    /// Keep comments posted before the date (JST)
+    #[clap(long)]
+    until: Option<datetime::DateTime>,
+    /// Compress output files: gzip, zstd [default: extension of the output file]
+    #[clap(long)]
+    compress: Option<compress::Compression>,
+    /// Write metadata of each video as JSON next to the comment file: <name>.info.json
+    #[clap(long)]
+    write_info: bool,
+    /// Save the largest thumbnail of each video next to the comment file: <name>.jpg
+    #[clap(long)]
+    write_thumbnail: bool,
+    /// Download comments of videos matching the keyword in the snapshot search API
+    #[clap(long)]
+    search: Option<String>,
+    /// Download comments of videos with the tag in the snapshot search API
+    #[clap(long)]
+    tag: Option<String>,
+    /// Sort order of the search: -viewCounter, +startTime, -commentCounter, ...
+    #[clap(long, default_value = "-viewCounter")]
+    sort: nicodo::SearchSort,
+    /// Search videos posted at or after the date (JST): 2019-01-01, 2019-01-01 12:00:00
+    #[clap(long)]
+    posted_since: Option<datetime::DateTime>,
+    /// Search videos posted before the date (JST)
+    #[clap(long)]
+    posted_until: Option<datetime::DateTime>,
+    /// Maximum number of videos of the search
+    #[clap(long, default_value = "100")]
+    max_results: usize,
+    /// Video ID, video URL, channel URL, or series URL
     ids: Vec<id::Id>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Convert a comment file into another format
+    Convert(convert::Opts),
+    /// Show statistics of comments of a video or a comment file
+    Stats(stats::Opts),
 }
 
 impl Opts {
@@ -86,6 +165,27 @@ impl Opts {
             Ok(process::Timespan::Latest)
         }
     }
+
+    fn searches(&self) -> Result<Vec<nicodo::SearchQuery>> {
+        let date = |d: Option<&datetime::DateTime>| {
+            d.map(|d| d.absolute().ok_or(Error::SearchDate)).transpose()
+        };
+        let since = date(self.posted_since.as_ref())?;
+        let until = date(self.posted_until.as_ref())?;
+        Ok([(self.search.as_ref(), false), (self.tag.as_ref(), true)]
+            .into_iter()
+            .filter_map(|(keyword, tag)| {
+                Some(nicodo::SearchQuery {
+                    keyword: keyword?.clone(),
+                    tag,
+                    sort: self.sort.clone(),
+                    since,
+                    until,
+                    max_results: self.max_results,
+                })
+            })
+            .collect())
+    }
 }
 
 async fn main2() -> Result<()> {
@@ -98,6 +198,61 @@ async fn main2() -> Result<()> {
     {
         return Err(Error::Period);
     }
+    if let (Some(start), Some(end)) = (opts.start.as_ref(), opts.end.as_ref()) {
+        if start.is_after(end) {
+            return Err(Error::PeriodOrder);
+        }
+    }
+    if opts
+        .interval
+        .as_ref()
+        .is_some_and(|i| i.duration() <= chrono::Duration::zero())
+    {
+        return Err(Error::Interval);
+    }
+
+    // reject options which cannot render subtitles before downloading anything
+    opts.format_options.ass().validate()?;
+    opts.format_options.subtitle().validate()?;
+
+    // a database is written in place
+    #[cfg(feature = "sqlite")]
+    if opts.output == "-" && matches!(opts.format, process::Format::SQLite) {
+        return Err(Error::StdoutFormat);
+    }
+
+    if let Some(Command::Convert(opts)) = opts.command.as_ref() {
+        return convert::convert(opts);
+    }
+
+    if let Some(Command::Stats(opts)) = opts.command.as_ref() {
+        if opts.is_file()? {
+            return stats::stats(opts, None).await;
+        }
+    }
+
+    let searches = opts.searches()?;
+
+    // documents of XML, JSON and the other formats cannot be concatenated
+    if opts.output == "-" {
+        if opts.update {
+            return Err(Error::StdoutUpdate);
+        }
+        let videos = opts
+            .ids
+            .iter()
+            .try_fold(0, |n, id| match id {
+                id::Id::Video(_) => Some(n + 1),
+                _ => None,
+            })
+            .filter(|_| searches.is_empty());
+        if !matches!(opts.format, process::Format::JSONL) && videos.is_none_or(|n| n > 1) {
+            return Err(Error::StdoutVideos);
+        }
+    }
+    if opts.update && !opts.format.can_update() {
+        return Err(Error::UpdateFormat);
+    }
 
     let mut conf = config::Config::load()?;
     let mut conf_changed = false;
@@ -127,17 +282,73 @@ async fn main2() -> Result<()> {
 
     let session = nicodo::Session::from_user_session(&conf.session);
 
+    if let Some(Command::Stats(opts)) = opts.command.as_ref() {
+        return stats::stats(opts, Some(&session)).await;
+    }
+
     let options = process::Options {
         quiet,
         session,
         timespan: opts.timespan()?,
         format: opts.format,
+        format_options: opts.format_options,
         output: opts.output,
+        output_template: opts.output_template,
+        compress: opts.compress,
+        write_info: opts.write_info,
+        write_thumbnail: opts.write_thumbnail,
+        ng_filter: opts
+            .ng_file
+            .as_ref()
+            .map(|path| -> Result<_> {
+                Ok(nicodo::NgFilter::parse(&std::fs::read_to_string(path)?)?)
+            })
+            .transpose()?,
+        from: opts.from,
+        to: opts.to,
+        since: opts.since,
+        until: opts.until,
         delay: Some(opts.delay),
+        skip_existing: opts.skip_existing,
+        update: opts.update,
+        archive: opts
+            .download_archive
+            .as_ref()
+            .map(archive::Archive::load)
+            .transpose()?,
     };
 
-    for item in opts.ids {
-        process::process(&item, &options).await?;
+    let mut report = report::Report::new();
+    let mut res = Ok(());
+    for item in &opts.ids {
+        res = process::process(item, &options, &mut report).await;
+        if res.is_err() {
+            break;
+        }
+    }
+    if res.is_ok() {
+        for query in &searches {
+            res = process::process_search(query, &options, &mut report).await;
+            if res.is_err() {
+                break;
+            }
+        }
+    }
+
+    report.finish(res.as_ref().err());
+    if let Some(path) = opts.report.as_ref() {
+        report.save(path)?;
+    }
+    res?;
+
+    if options.ng_filter.is_some() && !quiet {
+        eprintln!(
+            "Removed {} comments by NG rules (words: {}, regex: {}, user IDs: {})",
+            report.removed.total(),
+            report.removed.word,
+            report.removed.regex,
+            report.removed.user_id
+        );
     }
 
     if !quiet {
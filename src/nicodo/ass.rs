@@ -0,0 +1,331 @@
+use super::{error, Color, Comment, Position, Result, Size};
+use std::io::Write;
+
+/// Seconds a comment fixed at the top or bottom stays on screen
+const FIXED_DURATION: f64 = 3.0;
+/// Font size ratios of the player (big: 39px, medium: 24px, small: 15px)
+const BIG_SCALE: f64 = 39.0 / 24.0;
+const SMALL_SCALE: f64 = 15.0 / 24.0;
+
+#[derive(Debug, Clone)]
+pub struct AssOptions {
+    pub width: u32,
+    pub height: u32,
+    pub font: String,
+    /// Font size of medium comments
+    pub font_size: u32,
+    /// Seconds a scrolling comment takes to cross the screen
+    pub duration: f64,
+}
+
+impl Default for AssOptions {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            font: "MS PGothic".to_string(),
+            font_size: 48,
+            duration: 4.0,
+        }
+    }
+}
+
+impl AssOptions {
+    /// Rejects a font size or duration of zero, with which rows and speeds cannot be computed
+    pub fn validate(&self) -> Result<()> {
+        if self.font_size == 0 {
+            return Err(error::Error::InvalidOptions("font size must be positive"));
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err(error::Error::InvalidOptions("duration must be positive"));
+        }
+        Ok(())
+    }
+}
+
+/// A comment occupying a row
+#[derive(Debug, Clone, Copy)]
+struct Occupant {
+    start: f64,
+    end: f64,
+    width: f64,
+    speed: f64,
+}
+
+/// Writes comments as Advanced SubStation Alpha subtitles that scroll like the niconico player.
+pub fn write_ass<W: Write>(mut writer: W, comments: &[Comment], opts: &AssOptions) -> Result<()> {
+    opts.validate()?;
+    write_header(&mut writer, opts).map_err(|_| error::Error::Serialization)?;
+
+    let width = opts.width as f64;
+    let height = opts.height as f64;
+    let rows = ((height / opts.font_size as f64) as usize).max(1);
+    let mut scroll: Vec<Option<Occupant>> = vec![None; rows];
+    let mut top: Vec<f64> = vec![0.0; rows];
+    let mut bottom: Vec<f64> = vec![0.0; rows];
+
+    let mut sorted = comments.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|c| c.vpos);
+
+    for c in sorted {
+        let cmd = &c.command;
+        if c.content.is_empty() || cmd.invisible {
+            continue;
+        }
+
+        let scale = match cmd.size {
+            Size::Medium => 1.0,
+            Size::Big => BIG_SCALE,
+            Size::Small => SMALL_SCALE,
+        };
+        let font_size = opts.font_size as f64 * scale;
+        let lines = c.content.lines().count().max(1);
+        let span =
+            ((lines as f64 * font_size / opts.font_size as f64).ceil() as usize).clamp(1, rows);
+        let text_width = text_width(&c.content) * font_size;
+        let start = c.vpos.max(0) as f64 / 100.0;
+
+        let (row, occupant) = match cmd.position {
+            Position::Naka => {
+                let duration = cmd.duration.unwrap_or(opts.duration);
+                let o = Occupant {
+                    start,
+                    end: start + duration,
+                    width: text_width,
+                    speed: (width + text_width) / duration,
+                };
+                let row = allocate(rows, span, |r| match scroll[r] {
+                    Some(p) => {
+                        if p.start + p.width / p.speed <= o.start
+                            && o.start + width / o.speed >= p.end
+                        {
+                            None
+                        } else {
+                            Some(p.end)
+                        }
+                    }
+                    None => None,
+                });
+                scroll[row..row + span].fill(Some(o));
+                (row, o)
+            }
+            Position::Ue | Position::Shita => {
+                let o = Occupant {
+                    start,
+                    end: start + cmd.duration.unwrap_or(FIXED_DURATION),
+                    width: text_width,
+                    speed: 0.0,
+                };
+                let busy = if cmd.position == Position::Ue {
+                    &mut top
+                } else {
+                    &mut bottom
+                };
+                let row = allocate(rows, span, |r| {
+                    if busy[r] <= o.start {
+                        None
+                    } else {
+                        Some(busy[r])
+                    }
+                });
+                busy[row..row + span].fill(o.end);
+                (row, o)
+            }
+        };
+
+        let y = match cmd.position {
+            Position::Shita => height - (row + span) as f64 * opts.font_size as f64,
+            _ => row as f64 * opts.font_size as f64,
+        };
+        let mut tags = match cmd.position {
+            Position::Naka => format!(
+                "\\move({},{},{},{})",
+                width.round(),
+                y.round(),
+                (-occupant.width).round(),
+                y.round()
+            ),
+            _ => format!("\\an8\\pos({},{})", (width / 2.0).round(), y.round()),
+        };
+        if cmd.size != Size::Medium {
+            tags.push_str(&format!("\\fs{}", font_size.round()));
+        }
+        if let Some(color) = cmd.color {
+            tags.push_str(&format!("\\c{}", ass_color(color)));
+            if color == Color(0x000000) {
+                tags.push_str(&format!("\\3c{}", ass_color(Color(0xFFFFFF))));
+            }
+        }
+
+        writeln!(
+            writer,
+            "Dialogue: 2,{},{},Default,,0,0,0,,{{{}}}{}",
+            ass_time(occupant.start),
+            ass_time(occupant.end),
+            tags,
+            escape(&c.content)
+        )
+        .map_err(|_| error::Error::Serialization)?;
+    }
+
+    Ok(())
+}
+
+fn write_header<W: Write>(writer: &mut W, opts: &AssOptions) -> std::io::Result<()> {
+    write!(
+        writer,
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {}\n\
+         PlayResY: {}\n\
+         WrapStyle: 2\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, \
+         BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, \
+         BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Default,{},{},&H00FFFFFF,&H00FFFFFF,&H00000000,&H00000000,\
+         0,0,0,0,100,100,0,0,1,2,0,7,0,0,0,1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        opts.width, opts.height, opts.font, opts.font_size
+    )
+}
+
+/// Returns the first row from which `span` rows are free. `busy` returns `None` for a free row,
+/// or the time the row gets free. If no rows are free, the row that gets free first is returned.
+fn allocate<F: Fn(usize) -> Option<f64>>(rows: usize, span: usize, busy: F) -> usize {
+    let mut best = (0, f64::INFINITY);
+    for row in 0..=(rows - span) {
+        let until = (row..row + span)
+            .filter_map(&busy)
+            .fold(None, |a: Option<f64>, b| Some(a.map_or(b, |a| a.max(b))));
+        match until {
+            None => return row,
+            Some(until) if until < best.1 => best = (row, until),
+            _ => {}
+        }
+    }
+    best.0
+}
+
+/// Estimates the width of the text in em
+fn text_width(text: &str) -> f64 {
+    text.lines()
+        .map(|l| {
+            l.chars()
+                .map(|c| if (c as u32) < 0x2E80 { 0.5 } else { 1.0 })
+                .sum::<f64>()
+        })
+        .fold(0.0, f64::max)
+}
+
+fn ass_time(t: f64) -> String {
+    let cs = (t * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+fn ass_color(Color(rgb): Color) -> String {
+    format!(
+        "&H{:02X}{:02X}{:02X}&",
+        rgb & 0xFF,
+        (rgb >> 8) & 0xFF,
+        (rgb >> 16) & 0xFF
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\u{200B}")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace("\r\n", "\n")
+        .replace('\n', "\\N")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(no: usize, vpos: isize, mail: Option<&str>, content: &str) -> Comment {
+        let c = Comment::test(no, vpos, content);
+        match mail {
+            Some(mail) => c.with_mail(mail),
+            None => c,
+        }
+    }
+
+    fn dialogues(comments: &[Comment]) -> Vec<String> {
+        let mut buf = vec![];
+        write_ass(&mut buf, comments, &AssOptions::default()).unwrap();
+        String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .filter(|l| l.starts_with("Dialogue:"))
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_write_ass() {
+        let res = dialogues(&[
+            comment(1, 100, None, "aaa"),
+            comment(2, 100, Some("184 red big"), "{b}\nc"),
+            comment(3, 200, Some("shita #00ff00"), "d"),
+            comment(4, 250, Some("ue"), "e"),
+            comment(5, 260, Some("ue small"), "f"),
+            comment(6, 270, Some("invisible"), "g"),
+        ]);
+        assert_eq!(
+            res,
+            vec![
+                "Dialogue: 2,0:00:01.00,0:00:05.00,Default,,0,0,0,,{\\move(1280,0,-72,0)}aaa",
+                "Dialogue: 2,0:00:01.00,0:00:05.00,Default,,0,0,0,,{\\move(1280,48,-117,48)\\fs78\\c&H0000FF&}\\{b\\}\\Nc",
+                "Dialogue: 2,0:00:02.00,0:00:05.00,Default,,0,0,0,,{\\an8\\pos(640,672)\\c&H00FF00&}d",
+                "Dialogue: 2,0:00:02.50,0:00:05.50,Default,,0,0,0,,{\\an8\\pos(640,0)}e",
+                "Dialogue: 2,0:00:02.60,0:00:05.60,Default,,0,0,0,,{\\an8\\pos(640,48)\\fs30}f",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let write = |opts: AssOptions| write_ass(vec![], &[comment(1, 100, None, "a")], &opts);
+        assert!(write(AssOptions {
+            font_size: 0,
+            ..Default::default()
+        })
+        .is_err());
+        assert!(write(AssOptions {
+            duration: 0.0,
+            ..Default::default()
+        })
+        .is_err());
+        assert!(write(AssOptions {
+            duration: f64::NAN,
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_allocate() {
+        assert_eq!(allocate(3, 1, |_| None), 0);
+        assert_eq!(allocate(3, 1, |r| if r == 0 { Some(1.0) } else { None }), 1);
+        assert_eq!(allocate(4, 2, |r| if r == 1 { Some(1.0) } else { None }), 2);
+        assert_eq!(allocate(3, 1, |r| Some(3.0 - r as f64)), 2);
+    }
+
+    #[test]
+    fn test_ass_time() {
+        assert_eq!(ass_time(0.0), "0:00:00.00");
+        assert_eq!(ass_time(3723.45), "1:02:03.45");
+    }
+}
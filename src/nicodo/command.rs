@@ -0,0 +1,192 @@
+use serde::{Serialize, Serializer};
+
+/// Attributes of a comment parsed from its mail field, such as `184 shita red big`.
+/// When commands of the same kind conflict, the first one takes effect.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CommentCommand {
+    pub position: Position,
+    pub size: Size,
+    pub color: Option<Color>,
+    pub font: Option<Font>,
+    /// `184`: the user ID is anonymized
+    pub anonymous: bool,
+    pub invisible: bool,
+    pub full: bool,
+    pub ender: bool,
+    pub patissier: bool,
+    /// `_live`: posted by the uploader during a live premiere
+    pub live: bool,
+    /// `@N`: seconds the comment is displayed
+    pub duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    #[default]
+    Naka,
+    Ue,
+    Shita,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Size {
+    #[default]
+    Medium,
+    Big,
+    Small,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Font {
+    Defont,
+    Mincho,
+    Gothic,
+}
+
+/// RGB color, serialized as `#RRGGBB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub u32);
+
+impl Color {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(Self(match name {
+            "white" => 0xFFFFFF,
+            "red" => 0xFF0000,
+            "pink" => 0xFF8080,
+            "orange" => 0xFFC000,
+            "yellow" => 0xFFFF00,
+            "green" => 0x00FF00,
+            "cyan" => 0x00FFFF,
+            "blue" => 0x0000FF,
+            "purple" => 0xC000FF,
+            "black" => 0x000000,
+            "white2" | "niconicowhite" => 0xCCCC99,
+            "red2" | "truered" => 0xCC0033,
+            "pink2" => 0xFF33CC,
+            "orange2" | "passionorange" => 0xFF6600,
+            "yellow2" | "madyellow" => 0x999900,
+            "green2" | "elementalgreen" => 0x00CC66,
+            "cyan2" => 0x00CCCC,
+            "blue2" | "marineblue" => 0x3399FF,
+            "purple2" | "nobleviolet" => 0x6633CC,
+            "black2" => 0x666666,
+            _ => {
+                let hex = name.strip_prefix('#')?;
+                if hex.len() != 6 {
+                    return None;
+                }
+                return u32::from_str_radix(hex, 16).ok().map(Self);
+            }
+        }))
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("#{:06X}", self.0))
+    }
+}
+
+impl CommentCommand {
+    pub fn parse(mail: &str) -> Self {
+        let mut cmd = Self::default();
+        let mut position = None;
+        let mut size = None;
+
+        for c in mail.split_whitespace() {
+            match c {
+                "naka" => set(&mut position, Position::Naka),
+                "ue" => set(&mut position, Position::Ue),
+                "shita" => set(&mut position, Position::Shita),
+                "medium" => set(&mut size, Size::Medium),
+                "big" => set(&mut size, Size::Big),
+                "small" => set(&mut size, Size::Small),
+                "defont" => set(&mut cmd.font, Font::Defont),
+                "mincho" => set(&mut cmd.font, Font::Mincho),
+                "gothic" => set(&mut cmd.font, Font::Gothic),
+                "184" => cmd.anonymous = true,
+                "invisible" => cmd.invisible = true,
+                "full" => cmd.full = true,
+                "ender" => cmd.ender = true,
+                "patissier" => cmd.patissier = true,
+                "_live" => cmd.live = true,
+                _ => {
+                    if let Some(d) = c.strip_prefix('@') {
+                        match d.parse::<f64>() {
+                            Ok(d) if d.is_finite() && d > 0.0 => set(&mut cmd.duration, d),
+                            _ => {}
+                        }
+                    } else if let Some(color) = Color::from_name(c) {
+                        set(&mut cmd.color, color);
+                    }
+                }
+            }
+        }
+
+        cmd.position = position.unwrap_or_default();
+        cmd.size = size.unwrap_or_default();
+        cmd
+    }
+}
+
+fn set<T>(value: &mut Option<T>, v: T) {
+    if value.is_none() {
+        *value = Some(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(CommentCommand::parse(""), CommentCommand::default());
+        assert_eq!(
+            CommentCommand::parse("184 shita red big"),
+            CommentCommand {
+                position: Position::Shita,
+                size: Size::Big,
+                color: Some(Color(0xFF0000)),
+                anonymous: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            CommentCommand::parse(
+                "ue #12abEF small mincho @2.5 invisible full ender patissier _live"
+            ),
+            CommentCommand {
+                position: Position::Ue,
+                size: Size::Small,
+                color: Some(Color(0x12ABEF)),
+                font: Some(Font::Mincho),
+                invisible: true,
+                full: true,
+                ender: true,
+                patissier: true,
+                live: true,
+                duration: Some(2.5),
+                ..Default::default()
+            }
+        );
+        let cmd = CommentCommand::parse("blue red shita ue #zzzzzz unknown");
+        assert_eq!(cmd.color, Some(Color(0x0000FF)));
+        assert_eq!(cmd.position, Position::Shita);
+        for mail in ["@NaN", "@inf", "@-3", "@0", "@x"] {
+            assert_eq!(CommentCommand::parse(mail).duration, None);
+        }
+        assert_eq!(CommentCommand::parse("@NaN @3").duration, Some(3.0));
+    }
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(
+            serde_json::to_string(&CommentCommand::parse("184 ue pink2")).unwrap(),
+            r##"{"position":"ue","size":"medium","color":"#FF33CC","font":null,"anonymous":true,"invisible":false,"full":false,"ender":false,"patissier":false,"live":false,"duration":null}"##
+        );
+    }
+}
@@ -1,13 +1,16 @@
 use super::{
     comment_body::{get_body, Options, WaybackOptions},
-    Error, Info, Result, Session, Wayback,
+    CommentCommand, Error, Info, Result, Session, Wayback,
 };
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 use tokio::time::sleep;
 
-const API_ENDPOINT: &'static str = "https://nvcomment.nicovideo.jp/legacy/api.json";
+const API_ENDPOINT: &str = "https://nvcomment.nicovideo.jp/legacy/api.json";
 
 #[derive(Debug, Deserialize)]
 struct Element {
@@ -22,13 +25,20 @@ pub struct Chat {
     date: isize,
     // leaf: Option<isize>,
     // date_usec: Option<isize>,
-    // anonymity: isize,
+    #[serde(default)]
+    fork: usize,
+    #[serde(default)]
+    anonymity: usize,
+    #[serde(default)]
+    premium: usize,
+    #[serde(default)]
+    score: isize,
     user_id: Option<String>,
     mail: Option<String>,
     content: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Comment {
     pub thread: String,
     pub no: usize,
@@ -37,6 +47,21 @@ pub struct Comment {
     pub user_id: Option<String>,
     pub content: String,
     pub mail: Option<String>,
+    /// 1 for comments in the uploader's thread
+    #[serde(default)]
+    pub fork: usize,
+    /// 1 for comments whose user ID is anonymized
+    #[serde(default)]
+    pub anonymity: usize,
+    /// 1 for comments posted by premium members
+    #[serde(default)]
+    pub premium: usize,
+    /// NG score, which gets lower as more users report the comment
+    #[serde(default)]
+    pub score: isize,
+    /// Parsed from `mail`
+    #[serde(skip_deserializing)]
+    pub command: CommentCommand,
 }
 
 #[derive(Debug)]
@@ -68,7 +93,8 @@ impl Session {
             None
         };
 
-        let mut comments: HashMap<usize, Comment> = HashMap::new();
+        // the same number is used in each thread, e.g. the owner thread and the main thread
+        let mut comments: HashMap<(String, usize), Comment> = HashMap::new();
         let wayback_iter = wayback.iter();
         let wayback_len = wayback_iter.len();
 
@@ -89,6 +115,7 @@ impl Session {
                 }),
             });
 
+            self.count_request();
             let res = reqwest::Client::new()
                 .post(API_ENDPOINT)
                 .body(body)
@@ -111,6 +138,11 @@ impl Session {
                     date: c.date,
                     user_id: c.user_id,
                     content: c.content.unwrap(),
+                    fork: c.fork,
+                    anonymity: c.anonymity,
+                    premium: c.premium,
+                    score: c.score,
+                    command: CommentCommand::parse(c.mail.as_deref().unwrap_or_default()),
                     mail: c.mail,
                 })
                 .collect::<Vec<_>>();
@@ -123,7 +155,7 @@ impl Session {
             });
 
             current_comments.into_iter().for_each(|c| {
-                comments.insert(c.no, c);
+                comments.insert((c.thread.clone(), c.no), c);
             });
 
             if let Some(d) = delay {
@@ -132,9 +164,129 @@ impl Session {
             }
         }
 
-        let mut comments: Vec<_> = comments.into_iter().map(|(_, c)| c).collect();
-        comments.sort_by(|a, b| a.vpos.cmp(&b.vpos));
+        let mut comments: Vec<_> = comments.into_values().collect();
+        comments.sort_by_key(|c| c.vpos);
 
         Ok(comments)
     }
 }
+
+/// Formats vpos as playback time: 2:03, 1:02:03
+pub fn playback_time(vpos: isize) -> String {
+    let s = vpos / 100;
+    if s >= 3600 {
+        format!("{}:{:02}:{:02}", s / 3600, s % 3600 / 60, s % 60)
+    } else {
+        format!("{}:{:02}", s / 60, s % 60)
+    }
+}
+
+#[cfg(test)]
+impl Comment {
+    /// Comment in thread 1 for tests; other fields are set with struct update syntax
+    pub(crate) fn test(no: usize, vpos: isize, content: &str) -> Self {
+        Self {
+            thread: "1".to_string(),
+            no,
+            vpos,
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets `mail` and the command parsed from it
+    pub(crate) fn with_mail(mut self, mail: &str) -> Self {
+        self.command = CommentCommand::parse(mail);
+        self.mail = Some(mail.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+impl super::info::Video {
+    /// Video sm9 for tests; other fields are left empty
+    pub(crate) fn test() -> Self {
+        Self {
+            id: "sm9".to_string(),
+            title: "title".to_string(),
+            description: String::new(),
+            count: Default::default(),
+            duration: 320,
+            thumbnail: Default::default(),
+            registered_at: chrono::NaiveDate::from_ymd(2007, 3, 6).and_hms(0, 33, 0),
+        }
+    }
+}
+
+/// Merges fetched comments into existing ones, adding only comments that are not present yet
+/// and are newer than the existing ones by `no` in the same thread or by `date`. Returns the
+/// merged comments and the number of added comments.
+pub fn merge_comments(existing: Vec<Comment>, fetched: Vec<Comment>) -> (Vec<Comment>, usize) {
+    let mut max_no = HashMap::new();
+    for c in &existing {
+        let no = max_no.entry(c.thread.as_str()).or_insert(c.no);
+        *no = c.no.max(*no);
+    }
+    let max_date = existing.iter().map(|c| c.date).max();
+    let keys: HashSet<_> = existing.iter().map(|c| (c.thread.as_str(), c.no)).collect();
+
+    let added = fetched
+        .into_iter()
+        .filter(|c| !keys.contains(&(c.thread.as_str(), c.no)))
+        .filter(|c| {
+            max_no.get(c.thread.as_str()).is_none_or(|&no| c.no > no) || Some(c.date) > max_date
+        })
+        .collect::<Vec<_>>();
+    let added_len = added.len();
+
+    let mut comments = existing;
+    comments.extend(added);
+    comments.sort_by_key(|c| c.vpos);
+
+    (comments, added_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_time() {
+        assert_eq!(playback_time(0), "0:00");
+        assert_eq!(playback_time(12300), "2:03");
+        assert_eq!(playback_time(372300), "1:02:03");
+    }
+
+    #[test]
+    fn test_merge_comments() {
+        let comment = |no: usize, date: isize| Comment {
+            date,
+            ..Comment::test(no, no as isize * 100, &no.to_string())
+        };
+
+        let (comments, added) = merge_comments(
+            vec![comment(1, 100), comment(2, 200)],
+            vec![comment(0, 50), comment(2, 201), comment(3, 300)],
+        );
+        assert_eq!(added, 1);
+        assert_eq!(
+            comments.iter().map(|c| c.no).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        // numbers are counted in each thread
+        let (comments, added) = merge_comments(
+            comments,
+            vec![Comment {
+                thread: "2".to_string(),
+                ..comment(1, 50)
+            }],
+        );
+        assert_eq!(added, 1);
+        assert_eq!(comments.len(), 4);
+
+        let (comments, added) = merge_comments(vec![], vec![comment(1, 100)]);
+        assert_eq!(added, 1);
+        assert_eq!(comments.len(), 1);
+    }
+}
@@ -73,7 +73,7 @@ pub fn get_body(opts: Options) -> String {
     let content = format!(
         "0-{}:{}",
         opts.info.video.duration / 60
-            + (if opts.info.video.duration % 60 > 0 {
+            + (if !opts.info.video.duration.is_multiple_of(60) {
                 1
             } else {
                 0
@@ -125,22 +125,10 @@ pub fn get_body(opts: Options) -> String {
         userkey: if t.is_thread_key_required || opts.wayback.is_some() {
             None
         } else {
-            if let Some(_) = opts.wayback {
-                None
-            } else {
-                Some(opts.info.comment.keys.user_key.to_string())
-            }
-        },
-        waybackkey: if let Some(w) = opts.wayback.as_ref() {
-            Some(w.waybackkey.to_string())
-        } else {
-            None
-        },
-        when: if let Some(w) = opts.wayback.as_ref() {
-            Some(w.wayback.timestamp())
-        } else {
-            None
+            Some(opts.info.comment.keys.user_key.to_string())
         },
+        waybackkey: opts.wayback.as_ref().map(|w| w.waybackkey.to_string()),
+        when: opts.wayback.as_ref().map(|w| w.wayback.timestamp()),
     };
 
     body.extend(
@@ -148,7 +136,7 @@ pub fn get_body(opts: Options) -> String {
             .comment
             .threads
             .iter()
-            .filter(|t| t.is_active && (!opts.wayback.is_some() || t.is_thread_key_required))
+            .filter(|t| t.is_active && (opts.wayback.is_none() || t.is_thread_key_required))
             .flat_map(|t| {
                 let mut threads: Vec<Element> = vec![];
 
@@ -185,7 +173,5 @@ pub fn get_body(opts: Options) -> String {
         content: format!("rf:{}", rs),
     }));
 
-    let content = serde_json::to_string(&body).unwrap();
-
-    content
+    serde_json::to_string(&body).unwrap()
 }
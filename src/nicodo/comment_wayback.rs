@@ -1,6 +1,9 @@
 use std::convert::TryInto;
 
 use chrono::{Duration, NaiveDateTime};
+use serde::{Serialize, Serializer};
+
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
 
 #[derive(Debug, Clone)]
 pub enum Wayback {
@@ -25,11 +28,80 @@ impl Wayback {
     }
 
     pub fn is_wayback(&self) -> bool {
-        if let Self::Latest = self {
-            false
-        } else {
-            true
+        !matches!(self, Self::Latest)
+    }
+
+    /// Drops snapshots at or before `date`, which hold no comments newer than it. Returns `None`
+    /// if no snapshot is left.
+    pub fn after(&self, date: NaiveDateTime) -> Option<Self> {
+        match *self {
+            Self::Latest => Some(Self::Latest),
+            Self::DateTime(dt) => (dt > date).then(|| self.clone()),
+            Self::Period {
+                start,
+                end,
+                interval,
+                include_latest,
+            } => {
+                if start > date || is_empty(start, end, interval) {
+                    return Some(self.clone());
+                }
+                // keep the snapshots on the same grid
+                let interval_ms = interval.num_milliseconds();
+                let skipped = (date - start).num_milliseconds() / interval_ms + 1;
+                let start = start + Duration::milliseconds(skipped * interval_ms);
+                if start <= end {
+                    Some(Self::Period {
+                        start,
+                        end,
+                        interval,
+                        include_latest,
+                    })
+                } else if include_latest {
+                    Some(Self::Latest)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl Serialize for Wayback {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Repr {
+            Latest,
+            DateTime {
+                datetime: String,
+            },
+            Period {
+                start: String,
+                end: String,
+                interval: i64,
+                include_latest: bool,
+            },
+        }
+
+        match self {
+            Self::Latest => Repr::Latest,
+            Self::DateTime(dt) => Repr::DateTime {
+                datetime: dt.format(DATETIME_FORMAT).to_string(),
+            },
+            Self::Period {
+                start,
+                end,
+                interval,
+                include_latest,
+            } => Repr::Period {
+                start: start.format(DATETIME_FORMAT).to_string(),
+                end: end.format(DATETIME_FORMAT).to_string(),
+                interval: interval.num_seconds(),
+                include_latest: *include_latest,
+            },
         }
+        .serialize(serializer)
     }
 }
 
@@ -79,7 +151,7 @@ impl Iterator for WaybackIter {
                 interval,
                 include_latest,
             } => {
-                if start >= end || interval.is_zero() {
+                if is_empty(start, end, interval) {
                     None
                 } else {
                     let d = start + self.duration;
@@ -112,15 +184,25 @@ impl ExactSizeIterator for WaybackIter {
                 end,
                 interval,
                 include_latest,
-            } => ((end - start).num_milliseconds() / interval.num_milliseconds()
-                + 1
-                + if include_latest { 1 } else { 0 })
-            .try_into()
-            .unwrap(),
+            } => {
+                if is_empty(start, end, interval) {
+                    return 0;
+                }
+                ((end - start).num_milliseconds() / interval.num_milliseconds()
+                    + 1
+                    + if include_latest { 1 } else { 0 })
+                .try_into()
+                .unwrap()
+            }
         }
     }
 }
 
+/// A period starting after its end or stepping by less than a millisecond has no snapshot
+fn is_empty(start: NaiveDateTime, end: NaiveDateTime, interval: Duration) -> bool {
+    start > end || interval.num_milliseconds() <= 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,6 +268,71 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn test_empty_period() {
+        let dt = |s: &str| NaiveDateTime::from_str(s).unwrap();
+        let period = |start: &str, interval: Duration| Wayback::Period {
+            start: dt(start),
+            end: dt("2019-11-03T00:00:00"),
+            interval,
+            include_latest: true,
+        };
+
+        for wayback in [
+            period("2019-11-10T00:00:00", Duration::days(1)),
+            period("2019-11-01T00:00:00", Duration::zero()),
+        ] {
+            let iter = wayback.iter();
+            assert_eq!(iter.len(), 0);
+            assert_eq!(iter.count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_after() {
+        let dt = |s: &str| NaiveDateTime::from_str(s).unwrap();
+        let period = |start: &str, include_latest: bool| Wayback::Period {
+            start: dt(start),
+            end: dt("2019-11-04T00:00:00"),
+            interval: Duration::hours(6),
+            include_latest,
+        };
+
+        let iter = period("2019-11-03T00:00:00", false)
+            .after(dt("2019-11-03T12:00:00"))
+            .unwrap()
+            .into_iter();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![
+                Some(dt("2019-11-03T18:00:00")),
+                Some(dt("2019-11-04T00:00:00"))
+            ]
+        );
+
+        let iter = period("2019-11-03T00:00:00", true)
+            .after(dt("2019-11-03T23:00:00"))
+            .unwrap()
+            .into_iter();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![Some(dt("2019-11-04T00:00:00")), None]
+        );
+
+        assert!(matches!(
+            period("2019-11-03T00:00:00", true).after(dt("2019-11-04T00:00:00")),
+            Some(Wayback::Latest)
+        ));
+        assert!(period("2019-11-03T00:00:00", false)
+            .after(dt("2019-11-04T00:00:00"))
+            .is_none());
+        assert!(Wayback::DateTime(dt("2019-11-03T00:00:00"))
+            .after(dt("2019-11-03T00:00:00"))
+            .is_none());
+    }
+
     #[test]
     fn test_period2() {
         let mut iter = Wayback::Period {
@@ -271,4 +418,22 @@ mod tests {
             9
         );
     }
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(
+            serde_json::to_string(&Wayback::Latest).unwrap(),
+            r#"{"type":"latest"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Wayback::Period {
+                start: NaiveDateTime::from_str("2019-11-03T00:00:00").unwrap(),
+                end: NaiveDateTime::from_str("2019-11-10T00:00:00").unwrap(),
+                interval: Duration::hours(6),
+                include_latest: true,
+            })
+            .unwrap(),
+            r#"{"type":"period","start":"2019-11-03T00:00:00","end":"2019-11-10T00:00:00","interval":21600,"include_latest":true}"#
+        );
+    }
 }
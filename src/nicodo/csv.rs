@@ -0,0 +1,191 @@
+use super::{error, jst_datetime, Comment, Result};
+use std::{borrow::Cow, io::Write, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Thread,
+    No,
+    Vpos,
+    /// Playback time converted from vpos: 1:23.45
+    Time,
+    Date,
+    /// Date in JST: 2019-01-01 12:00:00, or empty if out of range
+    DateTime,
+    UserId,
+    Mail,
+    Content,
+}
+
+impl Column {
+    pub const ALL: &'static [Column] = &[
+        Self::Thread,
+        Self::No,
+        Self::Vpos,
+        Self::Time,
+        Self::Date,
+        Self::DateTime,
+        Self::UserId,
+        Self::Mail,
+        Self::Content,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Thread => "thread",
+            Self::No => "no",
+            Self::Vpos => "vpos",
+            Self::Time => "time",
+            Self::Date => "date",
+            Self::DateTime => "datetime",
+            Self::UserId => "user_id",
+            Self::Mail => "mail",
+            Self::Content => "content",
+        }
+    }
+
+    fn value<'a>(&self, c: &'a Comment) -> Cow<'a, str> {
+        match self {
+            Self::Thread => Cow::from(&c.thread),
+            Self::No => Cow::from(c.no.to_string()),
+            Self::Vpos => Cow::from(c.vpos.to_string()),
+            Self::Time => {
+                let cs = c.vpos.max(0);
+                Cow::from(format!(
+                    "{}:{:02}.{:02}",
+                    cs / 6000,
+                    cs / 100 % 60,
+                    cs % 100
+                ))
+            }
+            Self::Date => Cow::from(c.date.to_string()),
+            Self::DateTime => Cow::from(
+                jst_datetime(c.date)
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default(),
+            ),
+            Self::UserId => Cow::from(c.user_id.as_deref().unwrap_or_default()),
+            Self::Mail => Cow::from(c.mail.as_deref().unwrap_or_default()),
+            Self::Content => Cow::from(&c.content),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|c| c.name() == s)
+            .copied()
+            .ok_or("invalid column")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub columns: Vec<Column>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            columns: Column::ALL.to_vec(),
+        }
+    }
+}
+
+/// Writes comments as CSV with a header row. Fields containing the delimiter, quotes or
+/// newlines are quoted, so TSV can be written by changing the delimiter.
+pub fn write_csv<W: Write>(mut writer: W, comments: &[Comment], opts: &CsvOptions) -> Result<()> {
+    let delimiter = opts.delimiter.to_string();
+    let mut write_row = |fields: Vec<Cow<str>>| {
+        writeln!(
+            writer,
+            "{}",
+            fields
+                .iter()
+                .map(|f| quote(f, opts.delimiter))
+                .collect::<Vec<_>>()
+                .join(&delimiter)
+        )
+        .map_err(|_| error::Error::Serialization)
+    };
+
+    write_row(opts.columns.iter().map(|c| Cow::from(c.name())).collect())?;
+    for c in comments {
+        write_row(opts.columns.iter().map(|col| col.value(c)).collect())?;
+    }
+
+    Ok(())
+}
+
+fn quote(field: &str, delimiter: char) -> Cow<'_, str> {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        Cow::from(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::from(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments() -> Vec<Comment> {
+        vec![Comment {
+            date: 1546311600,
+            user_id: Some("abc".to_string()),
+            ..Comment::test(2, 8345, "a,\"b\"\nc\td").with_mail("184 red")
+        }]
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut buf = vec![];
+        write_csv(&mut buf, &comments(), &CsvOptions::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "thread,no,vpos,time,date,datetime,user_id,mail,content\n\
+             1,2,8345,1:23.45,1546311600,2019-01-01 12:00:00,abc,184 red,\"a,\"\"b\"\"\nc\td\"\n"
+        );
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let mut buf = vec![];
+        write_csv(
+            &mut buf,
+            &comments(),
+            &CsvOptions {
+                delimiter: '\t',
+                columns: vec![Column::Time, Column::Content],
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "time\tcontent\n1:23.45\t\"a,\"\"b\"\"\nc\td\"\n"
+        );
+    }
+
+    #[test]
+    fn test_date_out_of_range() {
+        let mut buf = vec![];
+        write_csv(
+            &mut buf,
+            &[Comment {
+                date: isize::MAX,
+                ..Comment::test(1, 0, "a")
+            }],
+            &CsvOptions {
+                delimiter: ',',
+                columns: vec![Column::DateTime, Column::Content],
+            },
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "datetime,content\n,a\n");
+    }
+}
@@ -0,0 +1,191 @@
+use super::{error, playback_time, Comment, Result};
+use std::io::Write;
+
+/// Number of comments in each bucket of playback, like the density graph of the player
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Density {
+    /// Size of buckets in vpos
+    pub bucket: isize,
+    pub counts: Vec<usize>,
+}
+
+impl Density {
+    /// Counts comments in buckets covering the video of `duration` seconds. Comments after the
+    /// end, which are posted at the last frame, are counted in the last bucket.
+    pub fn new(comments: &[Comment], duration: usize, bucket: isize) -> Result<Self> {
+        if bucket <= 0 {
+            return Err(error::Error::InvalidOptions("bucket must be positive"));
+        }
+        let len = ((duration as isize * 100 + bucket - 1) / bucket).max(1) as usize;
+        let mut counts = vec![0; len];
+        for c in comments {
+            let i = (c.vpos.max(0) / bucket) as usize;
+            counts[i.min(len - 1)] += 1;
+        }
+        Ok(Self { bucket, counts })
+    }
+
+    fn start(&self, i: usize) -> f64 {
+        (i as isize * self.bucket) as f64 / 100.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DensitySvgOptions {
+    pub width: usize,
+    pub height: usize,
+    pub color: String,
+}
+
+impl Default for DensitySvgOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 200,
+            color: "#3d7dd9".to_string(),
+        }
+    }
+}
+
+/// Writes the density as CSV: start and end of each bucket in seconds, and the number of comments
+pub fn write_density_csv<W: Write>(mut writer: W, density: &Density) -> Result<()> {
+    let mut write = || -> std::io::Result<()> {
+        writeln!(writer, "start,end,comments")?;
+        for (i, count) in density.counts.iter().enumerate() {
+            writeln!(
+                writer,
+                "{},{},{}",
+                density.start(i),
+                density.start(i + 1),
+                count
+            )?;
+        }
+        Ok(())
+    };
+    write().map_err(|_| error::Error::Serialization)
+}
+
+/// Writes the density as a standalone SVG chart. The chart is scaled so that the busiest bucket
+/// reaches the top, and the length of the video and the maximum count are labeled below it.
+pub fn write_density_svg<W: Write>(
+    mut writer: W,
+    density: &Density,
+    opts: &DensitySvgOptions,
+) -> Result<()> {
+    const LABEL_HEIGHT: usize = 20;
+
+    let (w, h) = (opts.width as f64, opts.height as f64);
+    let max = density
+        .counts
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64;
+    let step = w / density.counts.len() as f64;
+
+    let mut path = format!("M0,{:.1}", h);
+    for (i, &count) in density.counts.iter().enumerate() {
+        let y = h - count as f64 * h / max;
+        path.push_str(&format!(
+            " L{:.1},{:.1} L{:.1},{:.1}",
+            i as f64 * step,
+            y,
+            (i + 1) as f64 * step,
+            y
+        ));
+    }
+    path.push_str(&format!(" L{:.1},{:.1} Z", w, h));
+
+    let mut write = || -> std::io::Result<()> {
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{th}" viewBox="0 0 {w} {th}">"#,
+            w = opts.width,
+            th = opts.height + LABEL_HEIGHT
+        )?;
+        writeln!(
+            writer,
+            r##"<rect width="{}" height="{}" fill="#ffffff"/>"##,
+            opts.width,
+            opts.height + LABEL_HEIGHT
+        )?;
+        writeln!(
+            writer,
+            r#"<path d="{}" fill="{c}" fill-opacity="0.5" stroke="{c}"/>"#,
+            path,
+            c = opts.color
+        )?;
+        let y = opts.height + LABEL_HEIGHT - 5;
+        writeln!(
+            writer,
+            r##"<g font-family="sans-serif" font-size="12" fill="#333333"><text x="0" y="{y}">0:00</text><text x="{}" y="{y}" text-anchor="middle">max {}</text><text x="{}" y="{y}" text-anchor="end">{}</text></g>"##,
+            opts.width / 2,
+            max,
+            opts.width,
+            playback_time(density.counts.len() as isize * density.bucket),
+            y = y
+        )?;
+        writeln!(writer, "</svg>")
+    };
+    write().map_err(|_| error::Error::Serialization)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments() -> Vec<Comment> {
+        [0, 500, 999, 1000, 2500, 9000]
+            .iter()
+            .map(|&vpos| Comment {
+                vpos,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_density() {
+        let density = Density::new(&comments(), 25, 1000).unwrap();
+        assert_eq!(density.counts, vec![3, 1, 2]);
+
+        let mut buf = vec![];
+        write_density_csv(&mut buf, &density).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "start,end,comments\n0,10,3\n10,20,1\n20,30,2\n"
+        );
+    }
+
+    #[test]
+    fn test_write_density_svg() {
+        let density = Density::new(&comments(), 25, 1000).unwrap();
+        let mut buf = vec![];
+        write_density_svg(
+            &mut buf,
+            &density,
+            &DensitySvgOptions {
+                width: 300,
+                height: 30,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let svg = String::from_utf8(buf).unwrap();
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="300" height="50""#)
+        );
+        assert!(svg.contains(
+            r#"d="M0,30.0 L0.0,0.0 L100.0,0.0 L100.0,20.0 L200.0,20.0 L200.0,10.0 L300.0,10.0 L300.0,30.0 Z""#
+        ));
+        assert!(svg.contains(">max 3<"));
+        assert!(svg.contains(">0:30<"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_invalid_bucket() {
+        assert!(Density::new(&comments(), 25, 0).is_err());
+    }
+}
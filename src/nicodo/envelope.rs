@@ -0,0 +1,90 @@
+use super::{error, info::Video, Comment, Result, Wayback};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::io::Write;
+
+/// Context of fetched comments, written around them by `write_json_envelope`
+#[derive(Debug, Clone)]
+pub struct Envelope<'a> {
+    pub video: &'a Video,
+    pub wayback: &'a Wayback,
+    pub fetched_at: DateTime<Local>,
+}
+
+/// Writes comments as a JSON object with the video and wayback they were fetched for.
+/// The comments are under `comments`, in the same form as `write_json`.
+pub fn write_json_envelope<W: Write>(
+    writer: W,
+    envelope: &Envelope,
+    comments: &[Comment],
+    pretty: bool,
+) -> Result<()> {
+    #[derive(Serialize)]
+    struct Repr<'a> {
+        video: &'a Video,
+        wayback: &'a Wayback,
+        fetched_at: String,
+        comments: &'a [Comment],
+    }
+
+    let repr = Repr {
+        video: envelope.video,
+        wayback: envelope.wayback,
+        fetched_at: envelope.fetched_at.to_rfc3339(),
+        comments,
+    };
+    if pretty {
+        serde_json::to_writer_pretty(writer, &repr)
+    } else {
+        serde_json::to_writer(writer, &repr)
+    }
+    .map_err(|_| error::Error::Serialization)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_write_json_envelope() {
+        let video = Video::test();
+        let envelope = Envelope {
+            video: &video,
+            wayback: &Wayback::Latest,
+            fetched_at: Local.timestamp(0, 0),
+        };
+
+        let mut buf = vec![];
+        write_json_envelope(&mut buf, &envelope, &[], false).unwrap();
+        let res = serde_json::from_slice::<serde_json::Value>(&buf).unwrap();
+        assert_eq!(
+            res["video"],
+            serde_json::json!({
+                "id": "sm9",
+                "title": "title",
+                "description": "",
+                "count": { "view": 0, "comment": 0, "mylist": 0, "like": 0 },
+                "duration": 320,
+                "thumbnail": { "url": "", "middle_url": null, "large_url": null },
+                "registered_at": "2007-03-06T00:33:00+09:00"
+            })
+        );
+        assert_eq!(res["wayback"], serde_json::json!({ "type": "latest" }));
+        assert_eq!(
+            DateTime::parse_from_rfc3339(res["fetched_at"].as_str().unwrap())
+                .unwrap()
+                .timestamp(),
+            0
+        );
+        assert_eq!(res["comments"], serde_json::json!([]));
+
+        let mut pretty = vec![];
+        write_json_envelope(&mut pretty, &envelope, &[], true).unwrap();
+        assert!(pretty.contains(&b'\n'));
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&pretty).unwrap(),
+            res
+        );
+    }
+}
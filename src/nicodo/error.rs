@@ -16,6 +16,15 @@ pub enum Error {
     NotAuthorized,
     #[error("serialization error")]
     Serialization,
+    #[error("deserialization error")]
+    Deserialization,
+    #[error("invalid options: {0}")]
+    InvalidOptions(&'static str),
+    #[error("invalid filter: {0}")]
+    InvalidFilter(regex::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("database: {0}")]
+    Database(rusqlite::Error),
 }
 
 impl From<reqwest::Error> for Error {
@@ -24,4 +33,11 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Database(err)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
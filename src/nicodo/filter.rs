@@ -0,0 +1,183 @@
+use super::{Comment, Error, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// NG rules dropping unwanted comments. Rules are written one per line:
+///
+/// ```text
+/// # comment
+/// word
+/// regex:^w{3,}$
+/// user:abcdefg
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NgFilter {
+    words: Vec<String>,
+    regexes: Vec<Regex>,
+    user_ids: HashSet<String>,
+}
+
+/// Number of comments removed by each kind of rules
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct NgCount {
+    pub word: usize,
+    pub regex: usize,
+    pub user_id: usize,
+}
+
+impl NgCount {
+    pub fn total(&self) -> usize {
+        self.word + self.regex + self.user_id
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        self.word += other.word;
+        self.regex += other.regex;
+        self.user_id += other.user_id;
+    }
+}
+
+impl NgFilter {
+    pub fn parse(rules: &str) -> Result<Self> {
+        let mut filter = Self::default();
+        for line in rules.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(re) = line.strip_prefix("regex:") {
+                filter
+                    .regexes
+                    .push(Regex::new(re).map_err(Error::InvalidFilter)?);
+            } else if let Some(user_id) = line.strip_prefix("user:") {
+                filter.user_ids.insert(user_id.to_string());
+            } else {
+                filter.words.push(line.to_string());
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Removes comments matching any rule. A comment matching several kinds of rules is
+    /// counted once, in the order of user IDs, words and regular expressions.
+    pub fn apply(&self, comments: Vec<Comment>) -> (Vec<Comment>, NgCount) {
+        let mut count = NgCount::default();
+        let comments = comments
+            .into_iter()
+            .filter(|c| {
+                if c.user_id
+                    .as_ref()
+                    .is_some_and(|u| self.user_ids.contains(u))
+                {
+                    count.user_id += 1;
+                } else if self.words.iter().any(|w| c.content.contains(w.as_str())) {
+                    count.word += 1;
+                } else if self.regexes.iter().any(|r| r.is_match(&c.content)) {
+                    count.regex += 1;
+                } else {
+                    return true;
+                }
+                false
+            })
+            .collect();
+        (comments, count)
+    }
+}
+
+/// Range of comments to keep. Lower bounds are inclusive, upper bounds are exclusive, and `None`
+/// is unbounded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommentRange {
+    pub vpos_from: Option<isize>,
+    pub vpos_to: Option<isize>,
+    /// Unix timestamp
+    pub date_from: Option<isize>,
+    pub date_to: Option<isize>,
+}
+
+impl CommentRange {
+    pub fn contains(&self, c: &Comment) -> bool {
+        let within = |v: isize, from: Option<isize>, to: Option<isize>| {
+            from.is_none_or(|f| v >= f) && to.is_none_or(|t| v < t)
+        };
+        within(c.vpos, self.vpos_from, self.vpos_to) && within(c.date, self.date_from, self.date_to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(user_id: &str, content: &str) -> Comment {
+        Comment {
+            user_id: Some(user_id.to_string()),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let filter = NgFilter::parse(
+            "# NG rules\n\
+             \n\
+             spam\n\
+             regex:^w{3,}$\n\
+             user:bad\n",
+        )
+        .unwrap();
+        let (comments, count) = filter.apply(vec![
+            comment("a", "hello"),
+            comment("a", "this is spam"),
+            comment("a", "wwww"),
+            comment("a", "ww"),
+            comment("bad", "spam"),
+        ]);
+        assert_eq!(
+            comments
+                .iter()
+                .map(|c| c.content.as_str())
+                .collect::<Vec<_>>(),
+            vec!["hello", "ww"]
+        );
+        assert_eq!(
+            count,
+            NgCount {
+                word: 1,
+                regex: 1,
+                user_id: 1
+            }
+        );
+        assert_eq!(count.total(), 3);
+    }
+
+    #[test]
+    fn test_range() {
+        let c = |vpos: isize, date: isize| Comment {
+            vpos,
+            date,
+            ..Default::default()
+        };
+        let range = CommentRange {
+            vpos_from: Some(9000),
+            vpos_to: Some(18000),
+            ..Default::default()
+        };
+        assert!(!range.contains(&c(8999, 0)));
+        assert!(range.contains(&c(9000, 0)));
+        assert!(!range.contains(&c(18000, 0)));
+
+        let range = CommentRange {
+            date_from: Some(100),
+            ..range
+        };
+        assert!(!range.contains(&c(9000, 99)));
+        assert!(range.contains(&c(9000, 100)));
+        assert!(CommentRange::default().contains(&c(-100, 0)));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(NgFilter::parse("regex:(").is_err());
+    }
+}
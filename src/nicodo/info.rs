@@ -2,25 +2,128 @@ use super::{Error, Result, Session};
 use chrono::NaiveDateTime;
 use lazy_static::lazy_static;
 use scraper::{Html, Selector};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
 pub struct Info {
     pub video: Video,
+    #[serde(default)]
+    pub tag: Tags,
+    #[serde(default)]
+    pub genre: Option<Genre>,
+    /// Uploader of a user video
+    #[serde(default)]
+    pub owner: Option<Owner>,
+    /// Channel of a channel video
+    #[serde(default)]
+    pub channel: Option<VideoChannel>,
     pub comment: Comment,
     pub client: Client,
     pub viewer: Viewer,
 }
 
-#[derive(Debug, Deserialize)]
+impl Info {
+    pub fn metadata(&self) -> Metadata<'_> {
+        Metadata {
+            video: &self.video,
+            tags: &self.tag.items,
+            genre: self.genre.as_ref(),
+            owner: self.owner.as_ref(),
+            channel: self.channel.as_ref(),
+        }
+    }
+}
+
+/// Metadata of a video written by `--write-info`
+#[derive(Debug, Serialize)]
+pub struct Metadata<'a> {
+    #[serde(flatten)]
+    pub video: &'a Video,
+    pub tags: &'a [Tag],
+    pub genre: Option<&'a Genre>,
+    pub owner: Option<&'a Owner>,
+    pub channel: Option<&'a VideoChannel>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Video {
     pub id: String,
     pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub count: VideoCount,
     pub duration: usize,
-    #[serde(rename = "registeredAt", with = "registered_at")]
+    #[serde(default)]
+    pub thumbnail: Thumbnail,
+    #[serde(rename(deserialize = "registeredAt"), with = "registered_at")]
     pub registered_at: NaiveDateTime,
 }
 
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct VideoCount {
+    #[serde(default)]
+    pub view: usize,
+    #[serde(default)]
+    pub comment: usize,
+    #[serde(default)]
+    pub mylist: usize,
+    #[serde(default)]
+    pub like: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Thumbnail {
+    #[serde(default)]
+    pub url: String,
+    #[serde(rename(deserialize = "middleUrl"))]
+    pub middle_url: Option<String>,
+    #[serde(rename(deserialize = "largeUrl"))]
+    pub large_url: Option<String>,
+}
+
+impl Thumbnail {
+    /// Returns the URL of the largest thumbnail, if any
+    pub fn largest(&self) -> Option<&str> {
+        self.large_url
+            .as_deref()
+            .or(self.middle_url.as_deref())
+            .or(Some(self.url.as_str()))
+            .filter(|u| !u.is_empty())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Tags {
+    pub items: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Tag {
+    pub name: String,
+    #[serde(rename(deserialize = "isLocked"), default)]
+    pub is_locked: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Genre {
+    pub key: String,
+    pub label: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Owner {
+    pub id: usize,
+    /// Missing for some deleted accounts
+    pub nickname: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VideoChannel {
+    pub id: String,
+    pub name: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Comment {
     pub keys: CommentKeys,
@@ -28,7 +131,7 @@ pub struct Comment {
 }
 
 impl Comment {
-    pub fn thread_id<'a>(&'a self) -> Option<String> {
+    pub fn thread_id(&self) -> Option<String> {
         self.threads
             .iter()
             .find(|t| t.is_thread_key_required)
@@ -73,7 +176,33 @@ pub struct Viewer {
     pub id: usize,
 }
 
+/// Downloaded thumbnail image
+#[derive(Debug)]
+pub struct ThumbnailImage {
+    pub data: Vec<u8>,
+    /// File extension taken from the content type: jpg
+    pub ext: &'static str,
+}
+
 impl Session {
+    /// Downloads the largest thumbnail of the video. Returns `None` if the video has none.
+    /// Thumbnails are served by a CDN, so the session cookie is not sent.
+    pub async fn get_thumbnail(&self, video: &Video) -> Result<Option<ThumbnailImage>> {
+        let url = match video.thumbnail.largest() {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+        self.count_request();
+        let res = self.client.get(url).send().await?.error_for_status()?;
+        let ext = image_ext(
+            res.headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok()),
+        );
+        let data = res.bytes().await?.to_vec();
+        Ok(Some(ThumbnailImage { data, ext }))
+    }
+
     pub async fn get_info(&self, id: &str) -> Result<Info> {
         lazy_static! {
             static ref SELECTOR: Selector = Selector::parse("[id=js-initial-watch-data]").unwrap();
@@ -95,7 +224,7 @@ impl Session {
             .and_then(|n| n.value().attr("data-api-data"))
             .ok_or(Error::InvalidWatchPage)?;
 
-        let info = serde_json::from_str::<Info>(&data).map_err(|err| Error::InvalidInfo(err))?;
+        let info = serde_json::from_str::<Info>(data).map_err(Error::InvalidInfo)?;
 
         if info.comment.keys.user_key.is_empty() {
             return Err(Error::NotAuthorized);
@@ -105,11 +234,31 @@ impl Session {
     }
 }
 
+/// Thumbnails are served as JPEG without an extension in the URL
+fn image_ext(content_type: Option<&str>) -> &'static str {
+    match content_type
+        .and_then(|t| t.split(';').next())
+        .map(|t| t.trim())
+    {
+        Some("image/png") => "png",
+        Some("image/webp") => "webp",
+        Some("image/gif") => "gif",
+        _ => "jpg",
+    }
+}
+
 mod registered_at {
     use chrono::NaiveDateTime;
-    use serde::{self, Deserialize, Deserializer};
+    use serde::{self, Deserialize, Deserializer, Serializer};
 
-    const FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S+09:00";
+    const FORMAT: &str = "%Y-%m-%dT%H:%M:%S+09:00";
+
+    pub fn serialize<S>(date: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&date.format(FORMAT).to_string())
+    }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
     where
@@ -119,3 +268,81 @@ mod registered_at {
         NaiveDateTime::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata() {
+        let info = serde_json::from_value::<Info>(serde_json::json!({
+            "video": {
+                "id": "sm9",
+                "title": "title",
+                "description": "description",
+                "count": { "view": 1, "comment": 2, "mylist": 3, "like": 4 },
+                "duration": 320,
+                "thumbnail": {
+                    "url": "https://example.com/9",
+                    "middleUrl": "https://example.com/9.M",
+                    "largeUrl": "https://example.com/9.L",
+                    "player": "https://example.com/9.L",
+                    "ogp": null
+                },
+                "registeredAt": "2007-03-06T00:33:00+09:00"
+            },
+            "tag": { "items": [{ "name": "tag", "isCategory": false, "isLocked": true }] },
+            "genre": { "key": "other", "label": "その他" },
+            "owner": { "id": 4, "nickname": "owner" },
+            "channel": null,
+            "comment": { "keys": { "userKey": "key" }, "threads": [] },
+            "client": { "watchId": "sm9" },
+            "viewer": { "id": 1 }
+        }))
+        .unwrap();
+        assert_eq!(
+            info.video.thumbnail.largest(),
+            Some("https://example.com/9.L")
+        );
+        assert_eq!(Thumbnail::default().largest(), None);
+
+        assert_eq!(
+            serde_json::to_value(info.metadata()).unwrap(),
+            serde_json::json!({
+                "id": "sm9",
+                "title": "title",
+                "description": "description",
+                "count": { "view": 1, "comment": 2, "mylist": 3, "like": 4 },
+                "duration": 320,
+                "thumbnail": {
+                    "url": "https://example.com/9",
+                    "middle_url": "https://example.com/9.M",
+                    "large_url": "https://example.com/9.L"
+                },
+                "registered_at": "2007-03-06T00:33:00+09:00",
+                "tags": [{ "name": "tag", "is_locked": true }],
+                "genre": { "key": "other", "label": "その他" },
+                "owner": { "id": 4, "nickname": "owner" },
+                "channel": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_names() {
+        let owner = serde_json::from_value::<Owner>(serde_json::json!({ "id": 4 })).unwrap();
+        assert_eq!(owner.nickname, None);
+        let channel = serde_json::from_value::<VideoChannel>(
+            serde_json::json!({ "id": "ch1", "name": null }),
+        )
+        .unwrap();
+        assert_eq!(channel.name, None);
+    }
+
+    #[test]
+    fn test_image_ext() {
+        assert_eq!(image_ext(Some("image/jpeg")), "jpg");
+        assert_eq!(image_ext(Some("image/png; charset=binary")), "png");
+        assert_eq!(image_ext(None), "jpg");
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+
+/// Time zone of niconico, in which dates are displayed and counted
+pub fn jst() -> FixedOffset {
+    FixedOffset::east(9 * 3600)
+}
+
+/// Converts a Unix timestamp, such as `Comment::date`, into a datetime in JST. Returns `None` if
+/// the timestamp is out of range.
+pub fn jst_datetime(timestamp: isize) -> Option<DateTime<FixedOffset>> {
+    jst().timestamp_opt(timestamp as i64, 0).single()
+}
+
+/// Converts a datetime in JST into a Unix timestamp
+pub fn jst_timestamp(dt: NaiveDateTime) -> isize {
+    (dt.timestamp() - jst().local_minus_utc() as i64) as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_jst() {
+        let dt = NaiveDate::from_ymd(2019, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(jst_timestamp(dt), 1546268400);
+        assert_eq!(jst_datetime(1546268400).unwrap().naive_local(), dt);
+        assert!(jst_datetime(isize::MAX).is_none());
+    }
+}
@@ -1,17 +1,47 @@
+mod ass;
 mod channel;
+mod command;
 mod comment;
 mod comment_body;
 mod comment_wayback;
+mod csv;
+mod density;
+mod envelope;
 mod error;
+mod filter;
 mod info;
+mod jst;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod search;
+mod series;
 mod session;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stats;
+mod subtitle;
 mod xml;
 // mod signin;
 
 pub use self::error::*;
+#[cfg(feature = "parquet")]
+pub use self::parquet::write_parquet;
+pub use ass::{write_ass, AssOptions};
 pub use channel::Channel;
-pub use comment::Comment;
+pub use command::{Color, CommentCommand, Font, Position, Size};
+pub use comment::{merge_comments, playback_time, Comment};
 pub use comment_wayback::Wayback;
-pub use info::Info;
+pub use csv::{write_csv, Column, CsvOptions};
+pub use density::{write_density_csv, write_density_svg, Density, DensitySvgOptions};
+pub use envelope::{write_json_envelope, Envelope};
+pub use filter::{CommentRange, NgCount, NgFilter};
+pub use info::{Info, Metadata, ThumbnailImage};
+pub use jst::{jst, jst_datetime, jst_timestamp};
+pub use search::{SearchQuery, SearchResult, SearchSort};
+pub use series::{Episode, Series};
 pub use session::Session;
-pub use xml::{write_json, write_xml};
+#[cfg(feature = "sqlite")]
+pub use sqlite::write_sqlite;
+pub use stats::{stats, Peak, Stats, StatsOptions};
+pub use subtitle::{write_srt, write_vtt, SubtitleOptions};
+pub use xml::{read_comments, read_json, read_jsonl, read_xml, write_json, write_jsonl, write_xml};
@@ -0,0 +1,164 @@
+use super::{error, info::Video, Comment, Result};
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    errors::ParquetError,
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedColumnWriter, SerializedFileWriter, SerializedRowGroupWriter},
+    },
+    schema::parser::parse_message_type,
+};
+use std::{io::Write, sync::Arc};
+
+/// Columns in the order they are written
+const SCHEMA: &str = "
+message comment {
+    REQUIRED BYTE_ARRAY video_id (UTF8);
+    REQUIRED BYTE_ARRAY video_title (UTF8);
+    REQUIRED INT64 video_duration;
+    REQUIRED INT64 video_registered_at (TIMESTAMP(MILLIS,false));
+    REQUIRED BYTE_ARRAY thread (UTF8);
+    REQUIRED INT64 no;
+    REQUIRED INT64 vpos;
+    REQUIRED INT64 date (TIMESTAMP(MILLIS,true));
+    OPTIONAL BYTE_ARRAY user_id (UTF8);
+    OPTIONAL BYTE_ARRAY mail (UTF8);
+    REQUIRED BYTE_ARRAY content (UTF8);
+}
+";
+
+/// Writes comments as a Parquet file with a row per comment. Metadata of the video is repeated
+/// in every row so that files of many videos can be queried together.
+pub fn write_parquet<W: Write>(writer: W, video: &Video, comments: &[Comment]) -> Result<()> {
+    write(writer, video, comments).map_err(|_| error::Error::Serialization)
+}
+
+fn write<W: Write>(writer: W, video: &Video, comments: &[Comment]) -> parquet::errors::Result<()> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(writer, schema, props)?;
+    let mut rg = writer.next_row_group()?;
+    let len = comments.len();
+
+    write_strings(&mut rg, vec![video.id.as_str(); len])?;
+    write_strings(&mut rg, vec![video.title.as_str(); len])?;
+    write_ints(&mut rg, vec![video.duration as i64; len])?;
+    write_ints(&mut rg, vec![video.registered_at.timestamp_millis(); len])?;
+    write_strings(&mut rg, comments.iter().map(|c| c.thread.as_str()))?;
+    write_ints(&mut rg, comments.iter().map(|c| c.no as i64))?;
+    write_ints(&mut rg, comments.iter().map(|c| c.vpos as i64))?;
+    write_ints(&mut rg, comments.iter().map(|c| c.date as i64 * 1000))?;
+    write_optional_strings(&mut rg, comments.iter().map(|c| c.user_id.as_deref()))?;
+    write_optional_strings(&mut rg, comments.iter().map(|c| c.mail.as_deref()))?;
+    write_strings(&mut rg, comments.iter().map(|c| c.content.as_str()))?;
+
+    rg.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+fn next_column<'a, W: Write>(
+    rg: &'a mut SerializedRowGroupWriter<'_, W>,
+) -> parquet::errors::Result<SerializedColumnWriter<'a>> {
+    rg.next_column()?
+        .ok_or_else(|| ParquetError::General("more columns than in the schema".to_string()))
+}
+
+fn write_ints<W: Write>(
+    rg: &mut SerializedRowGroupWriter<'_, W>,
+    values: impl IntoIterator<Item = i64>,
+) -> parquet::errors::Result<()> {
+    let values = values.into_iter().collect::<Vec<_>>();
+    let mut column = next_column(rg)?;
+    column
+        .typed::<Int64Type>()
+        .write_batch(&values, None, None)?;
+    column.close()
+}
+
+fn write_strings<'a, W: Write>(
+    rg: &mut SerializedRowGroupWriter<'_, W>,
+    values: impl IntoIterator<Item = &'a str>,
+) -> parquet::errors::Result<()> {
+    let values = values.into_iter().map(ByteArray::from).collect::<Vec<_>>();
+    let mut column = next_column(rg)?;
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&values, None, None)?;
+    column.close()
+}
+
+/// Writes an optional string column, in which `None` is written as null
+fn write_optional_strings<'a, W: Write>(
+    rg: &mut SerializedRowGroupWriter<'_, W>,
+    values: impl IntoIterator<Item = Option<&'a str>>,
+) -> parquet::errors::Result<()> {
+    let values = values.into_iter().collect::<Vec<_>>();
+    let levels = values
+        .iter()
+        .map(|v| v.is_some() as i16)
+        .collect::<Vec<_>>();
+    let values = values
+        .into_iter()
+        .flatten()
+        .map(ByteArray::from)
+        .collect::<Vec<_>>();
+    let mut column = next_column(rg)?;
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&values, Some(&levels), None)?;
+    column.close()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::{
+        file::reader::{FileReader, SerializedFileReader},
+        record::RowAccessor,
+    };
+
+    #[test]
+    fn test_write_parquet() {
+        let video = Video::test();
+        let comments = vec![
+            Comment {
+                date: 1173108780,
+                user_id: Some("abc".to_string()),
+                ..Comment::test(1, 100, "a")
+            },
+            Comment {
+                date: 1173108781,
+                ..Comment::test(2, 200, "b").with_mail("184")
+            },
+        ];
+
+        // test runs of other feature sets may write at the same time
+        let path = std::env::temp_dir().join(format!(
+            "nicodo_test_write_parquet_{}.parquet",
+            std::process::id()
+        ));
+        write_parquet(std::fs::File::create(&path).unwrap(), &video, &comments).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let rows = reader.get_row_iter(None).unwrap().collect::<Vec<_>>();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_string(0).unwrap(), "sm9");
+        assert_eq!(rows[0].get_string(1).unwrap(), "title");
+        assert_eq!(rows[0].get_long(2).unwrap(), 320);
+        assert_eq!(rows[0].get_timestamp_millis(3).unwrap(), 1173141180000);
+        assert_eq!(rows[1].get_long(5).unwrap(), 2);
+        assert_eq!(rows[1].get_long(6).unwrap(), 200);
+        assert_eq!(rows[1].get_timestamp_millis(7).unwrap(), 1173108781000);
+        assert_eq!(rows[0].get_string(8).unwrap(), "abc");
+        assert!(rows[1].get_string(8).is_err());
+        assert_eq!(rows[1].get_string(9).unwrap(), "184");
+        assert_eq!(rows[1].get_string(10).unwrap(), "b");
+    }
+}
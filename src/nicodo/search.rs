@@ -0,0 +1,190 @@
+use super::{Result, Session};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::str::FromStr;
+
+const URL: &str = "https://api.search.nicovideo.jp/api/v2/snapshot/video/contents/search";
+/// Maximum number of results in a response
+const LIMIT: usize = 100;
+/// Maximum offset accepted by the API
+const MAX_OFFSET: usize = 100_000;
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S+09:00";
+
+/// Query of the snapshot search API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub keyword: String,
+    /// Search tags exactly instead of titles, descriptions and tags
+    pub tag: bool,
+    pub sort: SearchSort,
+    /// Posting date of videos in JST, inclusive
+    pub since: Option<NaiveDateTime>,
+    /// Posting date of videos in JST, exclusive
+    pub until: Option<NaiveDateTime>,
+    pub max_results: usize,
+}
+
+/// Sort order: -viewCounter, +startTime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSort(String);
+
+impl Default for SearchSort {
+    fn default() -> Self {
+        Self("-viewCounter".to_string())
+    }
+}
+
+impl FromStr for SearchSort {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        const FIELDS: &[&str] = &[
+            "viewCounter",
+            "mylistCounter",
+            "commentCounter",
+            "likeCounter",
+            "startTime",
+            "lastCommentTime",
+            "lengthSeconds",
+        ];
+        let field = s.strip_prefix(['-', '+']).unwrap_or(s);
+        if !FIELDS.contains(&field) {
+            return Err("invalid sort order");
+        }
+        // descending order is explicit, and ascending order is the default of the API
+        Ok(Self(if s.starts_with('-') { s } else { field }.to_string()))
+    }
+}
+
+impl SearchQuery {
+    fn params(&self, offset: usize, limit: usize) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("q", self.keyword.clone()),
+            (
+                "targets",
+                if self.tag {
+                    "tagsExact"
+                } else {
+                    "title,description,tags"
+                }
+                .to_string(),
+            ),
+            ("fields", "contentId,title".to_string()),
+            ("_sort", self.sort.0.clone()),
+            ("_offset", offset.to_string()),
+            ("_limit", limit.to_string()),
+            ("_context", "nicodo".to_string()),
+        ];
+        if let Some(since) = self.since {
+            params.push((
+                "filters[startTime][gte]",
+                since.format(DATETIME_FORMAT).to_string(),
+            ));
+        }
+        if let Some(until) = self.until {
+            params.push((
+                "filters[startTime][lt]",
+                until.format(DATETIME_FORMAT).to_string(),
+            ));
+        }
+        params
+    }
+}
+
+/// Video found by a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    data: Vec<SearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchItem {
+    #[serde(rename = "contentId")]
+    content_id: String,
+    title: String,
+}
+
+impl Session {
+    /// Searches videos in the order of the query, fetching pages until `max_results` videos
+    pub async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        let mut videos = vec![];
+        while videos.len() < query.max_results && videos.len() < MAX_OFFSET {
+            let limit = LIMIT.min(query.max_results - videos.len());
+            // the search API needs no login, so the session cookie is not sent to its host
+            self.count_request();
+            let res = self
+                .client
+                .get(URL)
+                .header(reqwest::header::USER_AGENT, "nicodo")
+                .query(&query.params(videos.len(), limit))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<SearchResponse>()
+                .await?;
+
+            let len = res.data.len();
+            videos.extend(res.data.into_iter().map(|v| SearchResult {
+                id: v.content_id,
+                title: v.title,
+            }));
+            if len < limit {
+                break;
+            }
+        }
+        Ok(videos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_params() {
+        let query = SearchQuery {
+            keyword: "VOCALOID".to_string(),
+            tag: true,
+            sort: "+startTime".parse().unwrap(),
+            since: Some(NaiveDate::from_ymd(2019, 1, 1).and_hms(0, 0, 0)),
+            until: None,
+            max_results: 10,
+        };
+        assert_eq!(
+            query.params(100, 10),
+            vec![
+                ("q", "VOCALOID".to_string()),
+                ("targets", "tagsExact".to_string()),
+                ("fields", "contentId,title".to_string()),
+                ("_sort", "startTime".to_string()),
+                ("_offset", "100".to_string()),
+                ("_limit", "10".to_string()),
+                ("_context", "nicodo".to_string()),
+                (
+                    "filters[startTime][gte]",
+                    "2019-01-01T00:00:00+09:00".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(
+            "-viewCounter".parse::<SearchSort>().unwrap(),
+            SearchSort::default()
+        );
+        assert_eq!(
+            "commentCounter".parse::<SearchSort>().unwrap(),
+            SearchSort("commentCounter".to_string())
+        );
+        assert!("-title".parse::<SearchSort>().is_err());
+    }
+}
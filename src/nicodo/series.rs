@@ -0,0 +1,219 @@
+use super::{Result, Session};
+use serde::Deserialize;
+use std::future::Future;
+
+const PAGE_SIZE: usize = 100;
+/// Pages fetched at most, in case the total count keeps growing or is wrong
+const MAX_PAGES: usize = 100;
+
+#[derive(Debug)]
+pub struct Series {
+    pub id: String,
+    pub title: String,
+    pub episodes: Vec<Episode>,
+}
+
+/// Video in a series, numbered from 1 in the order of the series
+#[derive(Debug, PartialEq, Eq)]
+pub struct Episode {
+    pub number: usize,
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesResponse {
+    data: SeriesData,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesData {
+    detail: SeriesDetail,
+    #[serde(rename = "totalCount")]
+    total_count: usize,
+    items: Vec<SeriesItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesDetail {
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesItem {
+    video: SeriesVideo,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesVideo {
+    id: String,
+    title: String,
+}
+
+impl Session {
+    /// Lists every video in the series in order, fetching all pages up to `MAX_PAGES`
+    pub async fn get_series(&self, id: &str) -> Result<Series> {
+        let url = format!("https://nvapi.nicovideo.jp/v2/series/{}", id);
+        let (title, episodes) = fetch_pages(|page| {
+            let req = self
+                .get(&url)
+                .header("X-Frontend-Id", "6")
+                .header("X-Frontend-Version", "0")
+                .query(&[("page", page), ("pageSize", PAGE_SIZE)]);
+            async move {
+                let res = req
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<SeriesResponse>()
+                    .await?;
+                Ok(res.data)
+            }
+        })
+        .await?;
+
+        Ok(Series {
+            id: id.to_string(),
+            title,
+            episodes,
+        })
+    }
+}
+
+/// Fetches pages from 1 until all videos are listed, a page is empty or `MAX_PAGES` are fetched
+async fn fetch_pages<F, Fut>(mut fetch: F) -> Result<(String, Vec<Episode>)>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<SeriesData>>,
+{
+    let mut title = String::new();
+    let mut episodes = vec![];
+
+    for page in 1..=MAX_PAGES {
+        let data = fetch(page).await?;
+        title = data.detail.title;
+        let len = data.items.len();
+        let offset = episodes.len();
+        episodes.extend(number(data.items, offset));
+        if len == 0 || episodes.len() >= data.total_count {
+            break;
+        }
+    }
+
+    Ok((title, episodes))
+}
+
+/// Numbers videos of a page following the `offset` episodes of the previous pages
+fn number(items: Vec<SeriesItem>, offset: usize) -> Vec<Episode> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| Episode {
+            number: offset + i + 1,
+            id: item.video.id,
+            title: item.video.title,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Page of `len` videos numbered from `start` in a series of `total` videos
+    fn page(start: usize, len: usize, total: usize) -> SeriesData {
+        SeriesData {
+            detail: SeriesDetail {
+                title: "series".to_string(),
+            },
+            total_count: total,
+            items: (start..start + len)
+                .map(|i| SeriesItem {
+                    video: SeriesVideo {
+                        id: format!("sm{}", i),
+                        title: i.to_string(),
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pages() {
+        let pages = Cell::new(0);
+        let (title, episodes) = fetch_pages(|n| {
+            pages.set(n);
+            async move {
+                Ok(page(
+                    (n - 1) * PAGE_SIZE + 1,
+                    (250 - (n - 1) * PAGE_SIZE).min(PAGE_SIZE),
+                    250,
+                ))
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(pages.get(), 3);
+        assert_eq!(title, "series");
+        assert_eq!(episodes.len(), 250);
+        assert!(episodes
+            .iter()
+            .enumerate()
+            .all(|(i, e)| e.number == i + 1 && e.id == format!("sm{}", i + 1)));
+
+        // an empty page ends the series even if the total count is larger
+        let (_, episodes) = fetch_pages(|n| async move {
+            Ok(if n == 1 {
+                page(1, 2, 10)
+            } else {
+                page(3, 0, 10)
+            })
+        })
+        .await
+        .unwrap();
+        assert_eq!(episodes.len(), 2);
+
+        // a total count which is never reached stops at the cap
+        let (_, episodes) = fetch_pages(|n| {
+            pages.set(n);
+            async move { Ok(page(1, 1, usize::MAX)) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(pages.get(), MAX_PAGES);
+        assert_eq!(episodes.len(), MAX_PAGES);
+    }
+
+    #[test]
+    fn test_number() {
+        let res = serde_json::from_value::<SeriesResponse>(serde_json::json!({
+            "meta": { "status": 200 },
+            "data": {
+                "detail": { "id": 1, "title": "series" },
+                "totalCount": 102,
+                "items": [
+                    { "meta": { "id": "sm1" }, "video": { "id": "sm1", "title": "第101話" } },
+                    { "meta": { "id": "sm2" }, "video": { "id": "sm2", "title": "第102話" } }
+                ]
+            }
+        }))
+        .unwrap();
+        assert_eq!(res.data.detail.title, "series");
+        assert_eq!(
+            number(res.data.items, 100),
+            vec![
+                Episode {
+                    number: 101,
+                    id: "sm1".to_string(),
+                    title: "第101話".to_string()
+                },
+                Episode {
+                    number: 102,
+                    id: "sm2".to_string(),
+                    title: "第102話".to_string()
+                }
+            ]
+        );
+    }
+}
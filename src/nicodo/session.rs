@@ -1,6 +1,7 @@
 use super::Result;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 lazy_static! {
     static ref RE_THREAD: Regex = Regex::new(r"^threadkey=(.+?)&force_184=(.+?)$").unwrap();
@@ -10,6 +11,7 @@ lazy_static! {
 pub struct Session {
     pub cookie: String,
     pub client: reqwest::Client,
+    requests: AtomicUsize,
 }
 
 impl Session {
@@ -17,6 +19,7 @@ impl Session {
         Self {
             cookie: format!("user_session={}", user_session),
             client: reqwest::Client::new(),
+            requests: AtomicUsize::new(0),
         }
     }
 
@@ -24,10 +27,22 @@ impl Session {
         Self {
             cookie: cookie.to_string(),
             client: reqwest::Client::new(),
+            requests: AtomicUsize::new(0),
         }
     }
 
+    /// Number of HTTP requests sent with the session
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Counts a request which is not built by `get`
+    pub(crate) fn count_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub(crate) fn get<U: reqwest::IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
+        self.count_request();
         self.client
             .get(url)
             .header(reqwest::header::COOKIE, &self.cookie)
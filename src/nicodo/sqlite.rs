@@ -0,0 +1,120 @@
+use super::{info::Video, Comment, Result};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS videos (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    registered_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS comments (
+    video_id TEXT NOT NULL REFERENCES videos (id),
+    thread TEXT NOT NULL,
+    no INTEGER NOT NULL,
+    vpos INTEGER NOT NULL,
+    date INTEGER NOT NULL,
+    user_id TEXT,
+    mail TEXT,
+    content TEXT NOT NULL,
+    PRIMARY KEY (video_id, thread, no)
+);
+";
+
+/// Writes the video and its comments into a SQLite database, creating the tables if needed.
+/// Rows already in the database are updated, so the same database can be reused across runs.
+pub fn write_sqlite<P: AsRef<Path>>(path: P, video: &Video, comments: &[Comment]) -> Result<()> {
+    let mut conn = Connection::open(path)?;
+    write(&mut conn, video, comments)
+}
+
+fn write(conn: &mut Connection, video: &Video, comments: &[Comment]) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    tx.execute(
+        "INSERT INTO videos (id, title, duration, registered_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (id) DO UPDATE SET
+            title = excluded.title,
+            duration = excluded.duration,
+            registered_at = excluded.registered_at",
+        params![
+            video.id,
+            video.title,
+            video.duration,
+            video.registered_at.format("%Y-%m-%dT%H:%M:%S").to_string()
+        ],
+    )?;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO comments (video_id, thread, no, vpos, date, user_id, mail, content)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (video_id, thread, no) DO UPDATE SET
+                vpos = excluded.vpos,
+                date = excluded.date,
+                user_id = excluded.user_id,
+                mail = excluded.mail,
+                content = excluded.content",
+        )?;
+        for c in comments {
+            stmt.execute(params![
+                video.id, c.thread, c.no, c.vpos, c.date, c.user_id, c.mail, c.content
+            ])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(no: usize, content: &str) -> Comment {
+        Comment::test(no, 100, content)
+    }
+
+    #[test]
+    fn test_write_sqlite() {
+        let video = Video::test();
+        let mut conn = Connection::open_in_memory().unwrap();
+        write(&mut conn, &video, &[comment(1, "a"), comment(2, "b")]).unwrap();
+        write(&mut conn, &video, &[comment(2, "c"), comment(3, "d")]).unwrap();
+
+        let videos: Vec<(String, String, usize, String)> = conn
+            .prepare("SELECT * FROM videos")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            videos,
+            vec![(
+                "sm9".to_string(),
+                "title".to_string(),
+                320,
+                "2007-03-06T00:33:00".to_string()
+            )]
+        );
+
+        let comments: Vec<(usize, String)> = conn
+            .prepare("SELECT no, content FROM comments ORDER BY no")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            comments,
+            vec![
+                (1, "a".to_string()),
+                (2, "c".to_string()),
+                (3, "d".to_string())
+            ]
+        );
+    }
+}
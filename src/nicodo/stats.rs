@@ -0,0 +1,188 @@
+use super::{jst_datetime, Comment};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+/// vpos of a minute
+const MINUTE: isize = 6000;
+/// Length of windows in which "wara" comments are counted, in vpos
+const PEAK_WINDOW: isize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct StatsOptions {
+    /// Number of entries in rankings
+    pub top: usize,
+    /// Length of the video in seconds. Comments after it are counted in the last minute, and
+    /// `None` counts minutes up to the last comment.
+    pub duration: Option<usize>,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self {
+            top: 10,
+            duration: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub comments: usize,
+    /// Number of comments in each minute of playback, from the first minute
+    pub per_minute: Vec<usize>,
+    /// Number of comments posted on each day in JST: 2019-01-01. Dates out of range are not
+    /// counted.
+    pub per_day: Vec<(String, usize)>,
+    pub top_users: Vec<(String, usize)>,
+    /// Comments with the same content, ignoring surrounding spaces
+    pub frequent_comments: Vec<(String, usize)>,
+    /// Number of uses of each command in `mail`, such as `184` and `red`
+    pub commands: Vec<(String, usize)>,
+    /// Windows of playback with most "wara" comments (w, 草, 笑)
+    pub peaks: Vec<Peak>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Peak {
+    /// Start of the window in vpos
+    pub vpos: isize,
+    pub comments: usize,
+}
+
+/// Computes statistics of comments.
+pub fn stats(comments: &[Comment], opts: &StatsOptions) -> Stats {
+    lazy_static! {
+        static ref WARA: Regex = Regex::new(r"(^|[^a-zA-Z])[wWｗＷ]+\s*$|草|笑").unwrap();
+    }
+
+    let mut per_minute = vec![];
+    let mut per_day = BTreeMap::new();
+    let mut users = HashMap::new();
+    let mut contents = HashMap::new();
+    let mut commands = HashMap::new();
+    let mut wara = HashMap::new();
+
+    let last_minute = opts.duration.map(|d| d / 60);
+
+    for c in comments {
+        let minute = ((c.vpos.max(0) / MINUTE) as usize).min(last_minute.unwrap_or(usize::MAX));
+        if per_minute.len() <= minute {
+            per_minute.resize(minute + 1, 0);
+        }
+        per_minute[minute] += 1;
+
+        if let Some(dt) = jst_datetime(c.date) {
+            *per_day
+                .entry(dt.format("%Y-%m-%d").to_string())
+                .or_insert(0) += 1;
+        }
+
+        if let Some(user_id) = c.user_id.as_deref() {
+            *users.entry(user_id).or_insert(0) += 1;
+        }
+
+        let content = c.content.trim();
+        if !content.is_empty() {
+            *contents.entry(content).or_insert(0) += 1;
+        }
+
+        for command in c.mail.as_deref().unwrap_or_default().split_whitespace() {
+            *commands.entry(command).or_insert(0) += 1;
+        }
+
+        if WARA.is_match(&c.content) {
+            *wara.entry(c.vpos.max(0) / PEAK_WINDOW).or_insert(0) += 1;
+        }
+    }
+
+    Stats {
+        comments: comments.len(),
+        per_minute,
+        per_day: per_day.into_iter().collect(),
+        top_users: owned(ranking(users, opts.top)),
+        frequent_comments: owned(ranking(contents, opts.top)),
+        commands: owned(ranking(commands, usize::MAX)),
+        peaks: ranking(wara, opts.top)
+            .into_iter()
+            .map(|(w, comments)| Peak {
+                vpos: w * PEAK_WINDOW,
+                comments,
+            })
+            .collect(),
+    }
+}
+
+/// Sorts entries by count in descending order, breaking ties by key, and takes the top ones
+fn ranking<K: Ord + Hash>(counts: HashMap<K, usize>, top: usize) -> Vec<(K, usize)> {
+    let mut res = counts.into_iter().collect::<Vec<_>>();
+    res.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    res.truncate(top);
+    res
+}
+
+fn owned(entries: Vec<(&str, usize)>) -> Vec<(String, usize)> {
+    entries
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(vpos: isize, date: isize, user_id: &str, mail: &str, content: &str) -> Comment {
+        Comment {
+            vpos,
+            date,
+            user_id: Some(user_id.to_string()),
+            mail: Some(mail.to_string()),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let res = stats(
+            &[
+                comment(100, 1546268400, "a", "184", "hello"),
+                comment(200, 1546268400, "a", "184 red", "wwww"),
+                comment(1200, 1546354799, "b", "", "草"),
+                comment(13000, 1546354800, "c", "red", " hello "),
+                comment(1300, 1546354800, "c", "", "hello w"),
+                comment(99999999, 1546354800, "c", "", "show"),
+            ],
+            &StatsOptions {
+                top: 2,
+                duration: Some(150),
+            },
+        );
+        assert_eq!(
+            res,
+            Stats {
+                comments: 6,
+                per_minute: vec![4, 0, 2],
+                per_day: vec![("2019-01-01".to_string(), 3), ("2019-01-02".to_string(), 3)],
+                top_users: vec![("c".to_string(), 3), ("a".to_string(), 2)],
+                frequent_comments: vec![("hello".to_string(), 2), ("hello w".to_string(), 1)],
+                commands: vec![("184".to_string(), 2), ("red".to_string(), 2)],
+                peaks: vec![
+                    Peak {
+                        vpos: 1000,
+                        comments: 2
+                    },
+                    Peak {
+                        vpos: 0,
+                        comments: 1
+                    }
+                ],
+            }
+        );
+    }
+}
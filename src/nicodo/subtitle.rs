@@ -0,0 +1,216 @@
+use super::{error, Comment, Result};
+use std::io::Write;
+
+#[derive(Debug, Clone)]
+pub struct SubtitleOptions {
+    /// Seconds a comment stays on screen
+    pub duration: f64,
+    /// Maximum number of lines on screen at the same time. Comments exceeding it are dropped.
+    pub max_lines: usize,
+}
+
+impl Default for SubtitleOptions {
+    fn default() -> Self {
+        Self {
+            duration: 3.0,
+            max_lines: 5,
+        }
+    }
+}
+
+impl SubtitleOptions {
+    /// Rejects a duration which is not positive or no lines, with which no cue can be shown
+    pub fn validate(&self) -> Result<()> {
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            return Err(error::Error::InvalidOptions("duration must be positive"));
+        }
+        if self.max_lines == 0 {
+            return Err(error::Error::InvalidOptions("max lines must be positive"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Cue<'a> {
+    start: f64,
+    end: f64,
+    line: usize,
+    text: Vec<&'a str>,
+}
+
+/// Writes comments as SubRip subtitles.
+pub fn write_srt<W: Write>(
+    mut writer: W,
+    comments: &[Comment],
+    opts: &SubtitleOptions,
+) -> Result<()> {
+    opts.validate()?;
+    for (i, cue) in cues(comments, opts).iter().enumerate() {
+        write!(
+            writer,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            time(cue.start, ','),
+            time(cue.end, ','),
+            cue.text.join("\n")
+        )
+        .map_err(|_| error::Error::Serialization)?;
+    }
+
+    Ok(())
+}
+
+/// Writes comments as WebVTT subtitles. Each cue is placed on its own line from the top.
+pub fn write_vtt<W: Write>(
+    mut writer: W,
+    comments: &[Comment],
+    opts: &SubtitleOptions,
+) -> Result<()> {
+    opts.validate()?;
+    write!(writer, "WEBVTT\n\n").map_err(|_| error::Error::Serialization)?;
+
+    for cue in cues(comments, opts) {
+        write!(
+            writer,
+            "{} --> {} line:{}\n{}\n\n",
+            time(cue.start, '.'),
+            time(cue.end, '.'),
+            cue.line,
+            cue.text
+                .iter()
+                .map(|t| escape_vtt(t))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+        .map_err(|_| error::Error::Serialization)?;
+    }
+
+    Ok(())
+}
+
+/// Converts comments into cues, allocating lines so that at most `max_lines` lines are shown
+fn cues<'a>(comments: &'a [Comment], opts: &SubtitleOptions) -> Vec<Cue<'a>> {
+    let mut busy = vec![0.0; opts.max_lines];
+    let mut sorted = comments.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|c| c.vpos);
+
+    sorted
+        .into_iter()
+        .filter(|c| !c.command.invisible)
+        .filter_map(|c| {
+            let text = c
+                .content
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>();
+            let span = text.len();
+            if span == 0 || span > opts.max_lines {
+                return None;
+            }
+
+            let start = c.vpos.max(0) as f64 / 100.0;
+            let end = start + c.command.duration.unwrap_or(opts.duration);
+            let line = (0..=(opts.max_lines - span))
+                .find(|&l| busy[l..l + span].iter().all(|&b| b <= start))?;
+            busy[line..line + span].fill(end);
+
+            Some(Cue {
+                start,
+                end,
+                line,
+                text,
+            })
+        })
+        .collect()
+}
+
+fn time(t: f64, separator: char) -> String {
+    let ms = (t * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3600000,
+        ms / 60000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(vpos: isize, mail: &str, content: &str) -> Comment {
+        Comment::test(0, vpos, content).with_mail(mail)
+    }
+
+    fn comments() -> Vec<Comment> {
+        vec![
+            comment(100, "", "a"),
+            comment(150, "", "b\n\nc"),
+            comment(160, "", "dropped"),
+            comment(170, "invisible", "invisible"),
+            comment(400, "@1", "<d> & e"),
+        ]
+    }
+
+    fn opts() -> SubtitleOptions {
+        SubtitleOptions {
+            duration: 3.0,
+            max_lines: 3,
+        }
+    }
+
+    #[test]
+    fn test_write_srt() {
+        let mut buf = vec![];
+        write_srt(&mut buf, &comments(), &opts()).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "1\n00:00:01,000 --> 00:00:04,000\na\n\n\
+             2\n00:00:01,500 --> 00:00:04,500\nb\nc\n\n\
+             3\n00:00:04,000 --> 00:00:05,000\n<d> & e\n\n"
+        );
+    }
+
+    #[test]
+    fn test_write_vtt() {
+        let mut buf = vec![];
+        write_vtt(&mut buf, &comments(), &opts()).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "WEBVTT\n\n\
+             00:00:01.000 --> 00:00:04.000 line:0\na\n\n\
+             00:00:01.500 --> 00:00:04.500 line:1\nb\nc\n\n\
+             00:00:04.000 --> 00:00:05.000 line:0\n&lt;d&gt; &amp; e\n\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let write = |opts: SubtitleOptions| {
+            (
+                write_srt(vec![], &comments(), &opts).is_err(),
+                write_vtt(vec![], &comments(), &opts).is_err(),
+            )
+        };
+        for duration in [-5.0, 0.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(write(SubtitleOptions { duration, ..opts() }), (true, true));
+        }
+        assert_eq!(
+            write(SubtitleOptions {
+                max_lines: 0,
+                ..opts()
+            }),
+            (true, true)
+        );
+    }
+}
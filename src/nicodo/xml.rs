@@ -1,66 +1,494 @@
-use super::{error, Comment, Result};
+use super::{error, Comment, CommentCommand, Result};
 use quick_xml::{
     events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
-    Writer,
+    Reader, Writer,
+};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    io::{BufRead, Read, Write},
+    str::FromStr,
 };
-use std::io::Write;
 
-pub fn write_json<W: Write>(writer: W, comments: &[Comment]) -> Result<()> {
-    serde_json::to_writer(writer, comments).map_err(|_| error::Error::Serialization)?;
+pub fn write_json<W: Write>(writer: W, comments: &[Comment], pretty: bool) -> Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(writer, comments)
+    } else {
+        serde_json::to_writer(writer, comments)
+    }
+    .map_err(|_| error::Error::Serialization)?;
     Ok(())
 }
 
-pub fn write_xml<W: Write>(writer: W, comments: &[Comment]) -> Result<()> {
-    // let mut w = Writer::new(writer);
-    let mut w = Writer::new_with_indent(writer, ' ' as u8, 0);
+/// Writes comments as JSON Lines, one comment per line, so that the output can be appended to.
+pub fn write_jsonl<W: Write>(mut writer: W, comments: &[Comment]) -> Result<()> {
+    for c in comments {
+        serde_json::to_writer(&mut writer, c).map_err(|_| error::Error::Serialization)?;
+        writer
+            .write_all(b"\n")
+            .map_err(|_| error::Error::Serialization)?;
+    }
+    Ok(())
+}
 
-    for e in &[
-        Event::Decl(BytesDecl::new(b"1.0", Some(b"utf-8"), None)),
-        Event::Start(BytesStart::owned(b"packet".to_vec(), "packet".len())),
-        {
-            let mut e = BytesStart::owned(b"thread".to_vec(), "thread".len());
-            e.push_attribute(("last_res", &(comments.len() - 1).to_string() as &str));
-            e.push_attribute(("ticket", ""));
-            Event::Empty(e)
-        },
-        {
-            let mut e = BytesStart::owned(b"view_counter".to_vec(), "view_counter".len());
-            e.push_attribute(("video", "0"));
-            Event::Empty(e)
-        },
-    ] {
-        w.write_event(e).map_err(|_| error::Error::Serialization)?;
+pub fn read_jsonl<R: BufRead>(reader: R) -> Result<Vec<Comment>> {
+    reader
+        .lines()
+        .filter(|l| !matches!(l, Ok(l) if l.trim().is_empty()))
+        .map(|l| {
+            let l = l.map_err(|_| error::Error::Deserialization)?;
+            let mut c =
+                serde_json::from_str::<Comment>(&l).map_err(|_| error::Error::Deserialization)?;
+            c.command = CommentCommand::parse(c.mail.as_deref().unwrap_or_default());
+            Ok(c)
+        })
+        .collect()
+}
+
+/// Reads comments from JSON written by `write_json` or `write_json_envelope`, or from a response
+/// of the comment API.
+pub fn read_json<R: Read>(reader: R) -> Result<Vec<Comment>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Element {
+        Chat { chat: Comment },
+        Comment(Comment),
+        Other(serde::de::IgnoredAny),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Document {
+        Envelope { comments: Vec<Element> },
+        Array(Vec<Element>),
     }
 
-    for c in comments.iter() {
-        if c.content.is_empty() {
-            continue;
+    let elements = match serde_json::from_reader::<_, Document>(reader)
+        .map_err(|_| error::Error::Deserialization)?
+    {
+        Document::Envelope { comments } => comments,
+        Document::Array(elements) => elements,
+    };
+
+    Ok(elements
+        .into_iter()
+        .filter_map(|e| match e {
+            Element::Chat { chat } | Element::Comment(chat) => Some(chat),
+            Element::Other(_) => None,
+        })
+        .map(|mut c| {
+            c.command = CommentCommand::parse(c.mail.as_deref().unwrap_or_default());
+            c
+        })
+        .collect())
+}
+
+/// Reads comments from XML in the `<packet><chat>` format, which is written by `write_xml` and
+/// other niconico comment tools. Deleted comments are skipped.
+pub fn read_xml<R: BufRead>(reader: R) -> Result<Vec<Comment>> {
+    let mut r = Reader::from_reader(reader);
+    let mut buf = vec![];
+    let mut comments = vec![];
+    let mut thread = String::new();
+    let mut current: Option<Comment> = None;
+
+    loop {
+        match r
+            .read_event(&mut buf)
+            .map_err(|_| error::Error::Deserialization)?
+        {
+            Event::Start(e) | Event::Empty(e) if e.name() == b"thread" => {
+                if let Some(t) = attribute(&r, &e, b"thread")? {
+                    thread = t;
+                }
+            }
+            Event::Start(e) if e.name() == b"chat" => current = read_chat(&r, &e, &thread)?,
+            Event::Empty(e) if e.name() == b"chat" => comments.extend(read_chat(&r, &e, &thread)?),
+            Event::Text(e) => {
+                if let Some(c) = current.as_mut() {
+                    c.content.push_str(
+                        &e.unescape_and_decode(&r)
+                            .map_err(|_| error::Error::Deserialization)?,
+                    );
+                }
+            }
+            Event::CData(e) => {
+                if let Some(c) = current.as_mut() {
+                    c.content
+                        .push_str(r.decode(&e).map_err(|_| error::Error::Deserialization)?);
+                }
+            }
+            Event::End(e) if e.name() == b"chat" => comments.extend(current.take()),
+            Event::Eof => break,
+            _ => {}
         }
+        buf.clear();
+    }
 
-        w.write_event({
-            let mut e = BytesStart::owned(b"chat".to_vec(), "chat".len());
-            e.push_attribute(("date", &c.date.to_string() as &str));
-            e.push_attribute(("vpos", &c.vpos.to_string() as &str));
-            e.push_attribute(("no", &c.no.to_string() as &str));
-            if let Some(user_id) = c.user_id.as_ref() {
-                e.push_attribute(("user_id", &user_id.to_string() as &str));
+    Ok(comments)
+}
+
+/// Reads comments from XML, JSON or JSON Lines, detected from the first character.
+pub fn read_comments<R: BufRead>(mut reader: R) -> Result<Vec<Comment>> {
+    const BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+    let first = loop {
+        let buf = reader
+            .fill_buf()
+            .map_err(|_| error::Error::Deserialization)?;
+        if buf.is_empty() {
+            break None;
+        }
+        match buf
+            .iter()
+            .position(|b| !b.is_ascii_whitespace() && !BOM.contains(b))
+        {
+            Some(i) => {
+                let first = buf[i];
+                reader.consume(i);
+                break Some(first);
             }
-            if let Some(mail) = c.mail.as_ref() {
-                e.push_attribute(("mail", &mail.to_string() as &str));
+            None => {
+                let len = buf.len();
+                reader.consume(len);
             }
-            Event::Start(e)
-        })
-        .and_then(|_| {
-            w.write_event(Event::Text(BytesText::from_plain_str(
-                &c.content.to_string(),
-            )))
-        })
-        .and_then(|_| w.write_event(Event::End(BytesEnd::owned(b"chat".to_vec()))))
-        .map_err(|_| error::Error::Serialization)?;
+        }
+    };
+
+    match first {
+        Some(b'<') => read_xml(reader),
+        // either JSON Lines or an envelope, which spans multiple lines when pretty-printed
+        Some(b'{') => {
+            let mut buf = vec![];
+            reader
+                .read_to_end(&mut buf)
+                .map_err(|_| error::Error::Deserialization)?;
+            read_json(&buf[..]).or_else(|_| read_jsonl(&buf[..]))
+        }
+        _ => read_json(reader),
+    }
+}
+
+fn attribute<B: BufRead>(r: &Reader<B>, e: &BytesStart, key: &[u8]) -> Result<Option<String>> {
+    for a in e.attributes() {
+        let a = a.map_err(|_| error::Error::Deserialization)?;
+        if a.key == key {
+            return a
+                .unescape_and_decode_value(r)
+                .map(Some)
+                .map_err(|_| error::Error::Deserialization);
+        }
+    }
+    Ok(None)
+}
+
+fn read_chat<B: BufRead>(r: &Reader<B>, e: &BytesStart, thread: &str) -> Result<Option<Comment>> {
+    fn parse<T: FromStr>(v: &str) -> Result<T> {
+        v.parse().map_err(|_| error::Error::Deserialization)
+    }
+
+    let mut c = Comment {
+        thread: thread.to_string(),
+        ..Default::default()
+    };
+
+    for a in e.attributes() {
+        let a = a.map_err(|_| error::Error::Deserialization)?;
+        let v = a
+            .unescape_and_decode_value(r)
+            .map_err(|_| error::Error::Deserialization)?;
+        match a.key {
+            b"thread" => c.thread = v,
+            b"no" => c.no = parse(&v)?,
+            b"vpos" => c.vpos = parse(&v)?,
+            b"date" => c.date = parse(&v)?,
+            b"user_id" => c.user_id = Some(v),
+            b"mail" => c.mail = Some(v),
+            b"fork" => c.fork = parse(&v)?,
+            b"anonymity" => c.anonymity = parse(&v)?,
+            b"premium" => c.premium = parse(&v)?,
+            b"score" => c.score = parse(&v)?,
+            b"deleted" if v != "0" => return Ok(None),
+            _ => {}
+        }
+    }
+
+    c.command = CommentCommand::parse(c.mail.as_deref().unwrap_or_default());
+    Ok(Some(c))
+}
+
+/// Writes comments in the `<packet>` format returned by the legacy comment API, with a `<thread>`
+/// element for each thread. Text is written as is without indentation.
+pub fn write_xml<W: Write>(writer: W, comments: &[Comment]) -> Result<()> {
+    let mut w = Writer::new(writer);
+
+    // thread ids in order of appearance, with the last comment number
+    let mut threads: Vec<(&str, usize)> = vec![];
+    for c in comments {
+        match threads.iter_mut().find(|(t, _)| *t == c.thread) {
+            Some((_, last_res)) => *last_res = (*last_res).max(c.no),
+            None => threads.push((&c.thread, c.no)),
+        }
+    }
+
+    let mut events = vec![
+        Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)),
+        Event::Start(BytesStart::borrowed_name(b"packet")),
+    ];
+    for (thread, last_res) in threads {
+        let mut e = BytesStart::borrowed_name(b"thread");
+        e.push_attribute(("resultcode", "0"));
+        e.push_attribute(("thread", thread));
+        e.push_attribute(("last_res", last_res.to_string().as_str()));
+        e.push_attribute(("ticket", "0"));
+        e.push_attribute(("revision", "1"));
+        events.push(Event::Empty(e));
+    }
+    // players expect the element after the threads, but the view count is unknown here
+    let mut e = BytesStart::borrowed_name(b"view_counter");
+    e.push_attribute(("video", "0"));
+    events.push(Event::Empty(e));
+    for e in events {
+        w.write_event(e)
+            .and_then(|_| w.write(b"\n"))
+            .map_err(|_| error::Error::Serialization)?;
+    }
+
+    for c in comments {
+        let mut e = BytesStart::borrowed_name(b"chat");
+        e.push_attribute(("thread", c.thread.as_str()));
+        if c.fork != 0 {
+            e.push_attribute(("fork", c.fork.to_string().as_str()));
+        }
+        e.push_attribute(("no", c.no.to_string().as_str()));
+        e.push_attribute(("vpos", c.vpos.to_string().as_str()));
+        e.push_attribute(("date", c.date.to_string().as_str()));
+        if c.anonymity != 0 {
+            e.push_attribute(("anonymity", c.anonymity.to_string().as_str()));
+        }
+        if let Some(user_id) = c.user_id.as_deref() {
+            e.push_attribute(("user_id", user_id));
+        }
+        if let Some(mail) = c.mail.as_deref() {
+            e.push_attribute(("mail", valid_chars(mail).as_ref()));
+        }
+        if c.premium != 0 {
+            e.push_attribute(("premium", c.premium.to_string().as_str()));
+        }
+        if c.score != 0 {
+            e.push_attribute(("score", c.score.to_string().as_str()));
+        }
+
+        w.write_event(Event::Start(e))
+            .and_then(|_| {
+                w.write_event(Event::Text(BytesText::from_escaped_str(escape_text(
+                    &valid_chars(&c.content),
+                ))))
+            })
+            .and_then(|_| w.write_event(Event::End(BytesEnd::borrowed(b"chat"))))
+            .and_then(|_| w.write(b"\n"))
+            .map_err(|_| error::Error::Serialization)?;
     }
 
     w.write_event(Event::End(BytesEnd::borrowed(b"packet")))
+        .and_then(|_| w.write(b"\n"))
         .map_err(|_| error::Error::Serialization)?;
 
     Ok(())
 }
+
+/// Removes characters which cannot appear in XML 1.0 even if escaped: C0 controls other than tab
+/// and newlines, U+FFFE and U+FFFF
+fn valid_chars(s: &str) -> Cow<'_, str> {
+    let invalid = |c: char| {
+        (c < ' ' && !matches!(c, '\t' | '\n' | '\r')) || matches!(c, '\u{FFFE}' | '\u{FFFF}')
+    };
+    if s.contains(invalid) {
+        Cow::from(s.replace(invalid, ""))
+    } else {
+        Cow::from(s)
+    }
+}
+
+/// Escapes text content. Unlike attribute values, quotes are written as is.
+fn escape_text(s: &str) -> Cow<'_, str> {
+    if s.contains(['&', '<', '>']) {
+        Cow::from(
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        )
+    } else {
+        Cow::from(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Position;
+    use super::*;
+
+    fn comments() -> Vec<Comment> {
+        vec![
+            Comment {
+                date: 1262271600,
+                user_id: Some("abc".to_string()),
+                ..Comment::test(1, 100, "<hello> & \"world\"").with_mail("184 red")
+            },
+            Comment {
+                date: 1262271601,
+                ..Comment::test(2, 200, "2")
+            },
+        ]
+    }
+
+    #[test]
+    fn test_read_json() {
+        let mut buf = vec![];
+        write_json(&mut buf, &comments(), false).unwrap();
+        let res = read_json(&buf[..]).unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].content, "<hello> & \"world\"");
+        assert_eq!(res[0].mail.as_deref(), Some("184 red"));
+        assert!(res[0].command.anonymous);
+        assert_eq!(res[1].user_id, None);
+
+        let res = read_json(
+            &br#"[
+                {"ping":{"content":"rs:0"}},
+                {"thread":{"resultcode":0,"thread":"1","last_res":2}},
+                {"chat":{"thread":"1","no":1,"vpos":100,"date":1262271600,"anonymity":1,"user_id":"abc","mail":"184","content":"a"}},
+                {"chat":{"thread":"1","no":2,"vpos":200,"date":1262271601,"deleted":1}}
+            ]"#[..],
+        )
+        .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].no, 1);
+        assert_eq!(res[0].content, "a");
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let mut buf = vec![];
+        write_jsonl(&mut buf, &comments()).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buf).lines().count(), 2);
+        write_jsonl(&mut buf, &comments()[1..]).unwrap();
+        buf.extend(b"\n");
+
+        let res = read_jsonl(&buf[..]).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].content, "<hello> & \"world\"");
+        assert!(res[0].command.anonymous);
+        assert_eq!(res[2].no, 2);
+        assert_eq!(read_comments(&buf[..]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_read_xml() {
+        let mut buf = vec![];
+        write_xml(&mut buf, &comments()).unwrap();
+        let res = read_xml(&buf[..]).unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].no, 1);
+        assert_eq!(res[0].vpos, 100);
+        assert_eq!(res[0].date, 1262271600);
+        assert_eq!(res[0].content, "<hello> & \"world\"");
+        assert_eq!(res[0].user_id.as_deref(), Some("abc"));
+        assert_eq!(res[0].mail.as_deref(), Some("184 red"));
+        assert_eq!(res[1].mail, None);
+
+        let res = read_xml(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<packet><thread resultcode="0" thread="1234" last_res="3" ticket="0x0" revision="1" server_time="1262271600"/>
+<view_counter video="10" id="sm0" mylist="0"/>
+<chat thread="1234" no="1" vpos="100" date="1262271600" date_usec="1" mail="184 shita" user_id="abc" premium="1" anonymity="1">first &#x27;one&#x27;</chat>
+<chat no="2" vpos="150" date="1262271601" user_id="def"> spaced </chat>
+<chat thread="1234" no="3" vpos="200" date="1262271602" deleted="1"/>
+</packet>"#
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].thread, "1234");
+        assert_eq!(res[0].content, "first 'one'");
+        assert_eq!(res[0].mail.as_deref(), Some("184 shita"));
+        assert_eq!(res[0].command.position, Position::Shita);
+        assert_eq!(res[1].thread, "1234");
+        assert_eq!(res[1].content, " spaced ");
+        assert_eq!(res[1].mail, None);
+    }
+
+    #[test]
+    fn test_write_xml() {
+        let mut buf = vec![];
+        write_xml(&mut buf, &[]).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<packet>\n<view_counter video=\"0\"/>\n</packet>\n"
+        );
+
+        let mut comments = comments();
+        comments[0].anonymity = 1;
+        comments[0].premium = 1;
+        comments[0].score = -1000;
+        comments[1].thread = "2".to_string();
+        comments[1].fork = 1;
+        comments[1].content = String::new();
+        comments.push(Comment::test(3, 0, " a\u{8}\u{85}\n b\u{FFFE} "));
+
+        let mut buf = vec![];
+        write_xml(&mut buf, &comments).unwrap();
+        assert_eq!(
+            String::from_utf8(buf.clone()).unwrap(),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<packet>
+<thread resultcode="0" thread="1" last_res="3" ticket="0" revision="1"/>
+<thread resultcode="0" thread="2" last_res="2" ticket="0" revision="1"/>
+<view_counter video="0"/>
+<chat thread="1" no="1" vpos="100" date="1262271600" anonymity="1" user_id="abc" mail="184 red" premium="1" score="-1000">&lt;hello&gt; &amp; "world"</chat>
+<chat thread="2" fork="1" no="2" vpos="200" date="1262271601"></chat>
+<chat thread="1" no="3" vpos="0" date="0"> a{}
+ b </chat>
+</packet>
+"#,
+                '\u{85}'
+            )
+        );
+
+        let res = read_xml(&buf[..]).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].anonymity, 1);
+        assert_eq!(res[0].premium, 1);
+        assert_eq!(res[0].score, -1000);
+        assert_eq!(res[1].thread, "2");
+        assert_eq!(res[1].fork, 1);
+        assert_eq!(res[1].content, "");
+        assert_eq!(res[2].content, " a\u{85}\n b ");
+    }
+
+    #[test]
+    fn test_read_comments() {
+        let mut buf = vec![];
+        write_xml(&mut buf, &comments()).unwrap();
+        assert_eq!(read_comments(&buf[..]).unwrap().len(), 2);
+
+        let mut buf = b"\xEF\xBB\xBF\n".to_vec();
+        write_json(&mut buf, &comments(), false).unwrap();
+        assert_eq!(read_comments(&buf[..]).unwrap().len(), 2);
+
+        let mut buf = vec![];
+        write_json(&mut buf, &comments(), true).unwrap();
+        assert_eq!(read_comments(&buf[..]).unwrap().len(), 2);
+
+        let res = read_comments(
+            &br#"{
+                "video": {"id": "sm9"},
+                "comments": [{"thread":"1","no":1,"vpos":100,"date":1262271600,"content":"a"}]
+            }"#[..],
+        )
+        .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].content, "a");
+    }
+}
@@ -1,7 +1,17 @@
 use super::id::Id;
-use crate::{datetime, error};
-use chrono::NaiveDateTime;
-use std::{borrow::Cow, convert::TryInto, path::Path, str::FromStr};
+use crate::{archive, compress, datetime, error, report, template};
+use chrono::{Local, NaiveDateTime};
+use clap::Args;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    convert::TryInto,
+    fmt::{self, Display},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
 use tokio::task::spawn_blocking;
 
 const DISPLAY_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
@@ -12,14 +22,76 @@ pub struct Options {
     pub session: nicodo::Session,
     pub timespan: Timespan,
     pub format: Format,
+    pub format_options: FormatOptions,
     pub output: String,
+    pub output_template: Option<template::Template>,
+    pub compress: Option<compress::Compression>,
+    pub write_info: bool,
+    pub write_thumbnail: bool,
+    pub ng_filter: Option<nicodo::NgFilter>,
+    pub from: Option<datetime::PlaybackTime>,
+    pub to: Option<datetime::PlaybackTime>,
+    pub since: Option<datetime::DateTime>,
+    pub until: Option<datetime::DateTime>,
     pub delay: Option<u64>,
+    pub skip_existing: bool,
+    pub update: bool,
+    pub archive: Option<archive::Archive>,
 }
 
+impl Options {
+    fn to_stdout(&self) -> bool {
+        self.output == "-"
+    }
+
+    /// Returns `None` if the output template uses a value which is unknown yet
+    fn dest(&self, vars: &template::Vars) -> Option<PathBuf> {
+        if self.format.is_database() {
+            return Some(Path::new(&self.output).join(format!("nicodo.{}", self.format.ext())));
+        }
+        let path = match self.output_template.as_ref() {
+            Some(t) => t.render(vars)?,
+            None => PathBuf::from(filename(vars.title, vars.wayback, &self.format)),
+        };
+        let path = Path::new(&self.output).join(path);
+        match self.compress {
+            Some(c) if compress::Compression::from_path(&path) != Some(c) => {
+                let mut path = path.into_os_string();
+                path.push(format!(".{}", c.ext()));
+                Some(PathBuf::from(path))
+            }
+            _ => Some(path),
+        }
+    }
+
+    /// Path of a file saved next to the comment file: title_latest.info.json. A database or
+    /// stdout holds comments of many videos, so the file is named after the video ID instead.
+    fn sidecar(&self, dest: &Path, id: &str, ext: &str) -> PathBuf {
+        if self.format.is_database() || self.to_stdout() {
+            let dir = if self.to_stdout() { "." } else { &self.output };
+            return Path::new(dir).join(format!("{}.{}", template::sanitize(id), ext));
+        }
+        let mut exts = vec![self.format.ext()];
+        exts.extend(compress::Compression::from_path(dest).map(|c| c.ext()));
+        replace_ext(dest, &exts, ext)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone)]
 pub enum Format {
     XML,
     JSON,
+    JSONL,
+    ASS,
+    SRT,
+    VTT,
+    CSV,
+    TSV,
+    #[cfg(feature = "sqlite")]
+    SQLite,
+    #[cfg(feature = "parquet")]
+    Parquet,
 }
 
 impl FromStr for Format {
@@ -29,6 +101,16 @@ impl FromStr for Format {
         match s {
             "xml" => Ok(Self::XML),
             "json" => Ok(Self::JSON),
+            "jsonl" => Ok(Self::JSONL),
+            "ass" => Ok(Self::ASS),
+            "srt" => Ok(Self::SRT),
+            "vtt" => Ok(Self::VTT),
+            "csv" => Ok(Self::CSV),
+            "tsv" => Ok(Self::TSV),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Self::SQLite),
+            #[cfg(feature = "parquet")]
+            "parquet" => Ok(Self::Parquet),
             _ => Err("invalid format"),
         }
     }
@@ -39,6 +121,152 @@ impl Format {
         match self {
             Self::XML => "xml",
             Self::JSON => "json",
+            Self::JSONL => "jsonl",
+            Self::ASS => "ass",
+            Self::SRT => "srt",
+            Self::VTT => "vtt",
+            Self::CSV => "csv",
+            Self::TSV => "tsv",
+            #[cfg(feature = "sqlite")]
+            Self::SQLite => "sqlite",
+            #[cfg(feature = "parquet")]
+            Self::Parquet => "parquet",
+        }
+    }
+
+    /// Whether comments of all videos are written into a single database file
+    fn is_database(&self) -> bool {
+        #[cfg(feature = "sqlite")]
+        if let Self::SQLite = self {
+            return true;
+        }
+        false
+    }
+
+    /// Whether `--update` can merge into an existing file, which needs the file to be read back
+    pub fn can_update(&self) -> bool {
+        matches!(self, Self::XML | Self::JSON | Self::JSONL) || self.is_database()
+    }
+
+    /// Writes comments. `envelope` is written around JSON comments with `--json-envelope`, and
+    /// `None` is an error in that case.
+    pub fn write<W: std::io::Write>(
+        &self,
+        writer: W,
+        comments: &[nicodo::Comment],
+        opts: &FormatOptions,
+        envelope: Option<&nicodo::Envelope>,
+    ) -> error::Result<()> {
+        match self {
+            Self::XML => nicodo::write_xml(writer, comments)?,
+            Self::JSON if opts.json_envelope => nicodo::write_json_envelope(
+                writer,
+                envelope.ok_or(error::Error::Envelope)?,
+                comments,
+                opts.pretty,
+            )?,
+            Self::JSON => nicodo::write_json(writer, comments, opts.pretty)?,
+            Self::JSONL => nicodo::write_jsonl(writer, comments)?,
+            Self::ASS => nicodo::write_ass(writer, comments, &opts.ass())?,
+            Self::SRT => nicodo::write_srt(writer, comments, &opts.subtitle())?,
+            Self::VTT => nicodo::write_vtt(writer, comments, &opts.subtitle())?,
+            Self::CSV => nicodo::write_csv(writer, comments, &opts.csv(','))?,
+            Self::TSV => nicodo::write_csv(writer, comments, &opts.csv('\t'))?,
+            #[cfg(feature = "sqlite")]
+            Self::SQLite => return Err(error::Error::UnwritableFormat),
+            #[cfg(feature = "parquet")]
+            Self::Parquet => return Err(error::Error::UnwritableFormat),
+        }
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> error::Result<Vec<nicodo::Comment>> {
+        let file = compress::open(path)?;
+        Ok(match self {
+            Self::XML => nicodo::read_xml(file)?,
+            Self::JSON => nicodo::read_json(file)?,
+            Self::JSONL => nicodo::read_jsonl(file)?,
+            _ => return Err(error::Error::UnreadableFormat),
+        })
+    }
+}
+
+/// Options for output formats
+#[derive(Debug, Clone, Args)]
+pub struct FormatOptions {
+    /// JSON: indent output
+    #[clap(long)]
+    pretty: bool,
+    /// JSON: wrap comments in an object with the video, wayback and fetch time
+    #[clap(long)]
+    json_envelope: bool,
+    /// ASS: video resolution
+    #[clap(long, default_value = "1280x720")]
+    ass_resolution: Resolution,
+    /// ASS: font name
+    #[clap(long, default_value = "MS PGothic")]
+    ass_font: String,
+    /// ASS: font size of medium comments
+    #[clap(long, default_value = "48")]
+    ass_font_size: u32,
+    /// ASS: seconds a scrolling comment takes to cross the screen
+    #[clap(long, default_value = "4")]
+    ass_duration: f64,
+    /// SRT, VTT: seconds a comment stays on screen
+    #[clap(long, default_value = "3")]
+    subtitle_duration: f64,
+    /// SRT, VTT: maximum number of lines on screen at the same time
+    #[clap(long, default_value = "5")]
+    subtitle_max_lines: usize,
+    /// CSV, TSV: columns to write [default: all] [possible values: thread, no, vpos, time, date,
+    /// datetime, user_id, mail, content]
+    #[clap(long, use_delimiter = true)]
+    columns: Vec<nicodo::Column>,
+}
+
+impl FormatOptions {
+    pub fn ass(&self) -> nicodo::AssOptions {
+        nicodo::AssOptions {
+            width: self.ass_resolution.0,
+            height: self.ass_resolution.1,
+            font: self.ass_font.clone(),
+            font_size: self.ass_font_size,
+            duration: self.ass_duration,
+        }
+    }
+
+    fn csv(&self, delimiter: char) -> nicodo::CsvOptions {
+        nicodo::CsvOptions {
+            delimiter,
+            columns: if self.columns.is_empty() {
+                nicodo::Column::ALL.to_vec()
+            } else {
+                self.columns.clone()
+            },
+        }
+    }
+
+    pub fn subtitle(&self) -> nicodo::SubtitleOptions {
+        nicodo::SubtitleOptions {
+            duration: self.subtitle_duration,
+            max_lines: self.subtitle_max_lines,
+        }
+    }
+}
+
+/// Resolution: 1280x720
+#[derive(Debug, Clone)]
+pub struct Resolution(u32, u32);
+
+impl FromStr for Resolution {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        const ERR: &str = "invalid resolution";
+        let (w, h) = s.split_once('x').ok_or(ERR)?;
+        match (w.parse(), h.parse()) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok(Self(w, h)),
+            _ => Err(ERR),
         }
     }
 }
@@ -74,6 +302,17 @@ impl Timespan {
         }
     }
 
+    /// Returns the wayback if it depends on neither the posted date of the video nor the current
+    /// time, which changes between the runs and so names a different file every time.
+    fn absolute_wayback(&self) -> Option<nicodo::Wayback> {
+        let is_absolute = |d: &datetime::DateTime| matches!(d, datetime::DateTime::DateTime(_));
+        match self {
+            Self::DateTime(d) if !is_absolute(d) => None,
+            Self::Period { start, end, .. } if !is_absolute(start) || !is_absolute(end) => None,
+            _ => Some(self.wayback(NaiveDateTime::from_timestamp(0, 0))),
+        }
+    }
+
     fn interval(&self) -> Option<&datetime::Duration> {
         if let Self::Period { interval, .. } = self {
             Some(interval)
@@ -83,30 +322,211 @@ impl Timespan {
     }
 }
 
-pub async fn process(item: &Id, opts: &Options) -> error::Result<()> {
-    for id in match item {
+impl Display for Timespan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DateTime(d) => write!(f, "{}", d),
+            Self::Period {
+                start,
+                end,
+                interval,
+                include_latest,
+            } => write!(
+                f,
+                "{}~{}/{}{}",
+                start,
+                end,
+                interval,
+                if *include_latest { "+latest" } else { "" }
+            ),
+            Self::Latest => write!(f, "latest"),
+        }
+    }
+}
+
+/// Channel or series which lists the videos, used by the output template
+#[derive(Debug, Clone, Copy, Default)]
+struct Listing<'a> {
+    channel: Option<&'a str>,
+    series: Option<&'a str>,
+}
+
+pub async fn process(item: &Id, opts: &Options, report: &mut report::Report) -> error::Result<()> {
+    let listing = match item {
+        Id::Channel(id) => Listing {
+            channel: Some(id),
+            ..Default::default()
+        },
+        Id::Series(id) => Listing {
+            series: Some(id),
+            ..Default::default()
+        },
+        Id::Video(_) => Listing::default(),
+    };
+
+    let videos = match item {
         Id::Channel(id) => {
             let res = opts.session.get_channel(id).await?;
 
             eprintln!("Channel: {} ({} videos)", &id, res.len());
 
-            res.into_iter().map(|v| Cow::from(v.id)).collect::<Vec<_>>()
+            res.into_iter()
+                .map(|v| (Cow::from(v.id), Some(v.title), None))
+                .collect::<Vec<_>>()
+        }
+        Id::Series(id) => {
+            let res = opts.session.get_series(id).await?;
+
+            eprintln!(
+                "Series: {} ({}, {} videos)",
+                &id,
+                res.title,
+                res.episodes.len()
+            );
+
+            res.episodes
+                .into_iter()
+                .map(|e| (Cow::from(e.id), Some(e.title), Some(e.number)))
+                .collect::<Vec<_>>()
         }
-        Id::Video(id) => vec![Cow::from(id)],
-    } {
-        process_video(&id, opts).await?;
+        Id::Video(id) => vec![(Cow::from(id), None, None)],
+    };
+
+    process_videos(videos, listing, opts, report).await
+}
+
+/// Processes videos found by `--search` or `--tag` in the order of the results
+pub async fn process_search(
+    query: &nicodo::SearchQuery,
+    opts: &Options,
+    report: &mut report::Report,
+) -> error::Result<()> {
+    let res = opts.session.search(query).await?;
+
+    eprintln!("Search: {} ({} videos)", &query.keyword, res.len());
+
+    let videos = res
+        .into_iter()
+        .map(|v| (Cow::from(v.id), Some(v.title), None))
+        .collect();
+    process_videos(videos, Listing::default(), opts, report).await
+}
+
+/// Processes videos given as their IDs, with titles and episode numbers if known in advance
+async fn process_videos(
+    videos: Vec<(Cow<'_, str>, Option<String>, Option<usize>)>,
+    listing: Listing<'_>,
+    opts: &Options,
+    report: &mut report::Report,
+) -> error::Result<()> {
+    for (id, title, episode) in videos {
+        process_video(&id, title.as_deref(), listing, episode, opts, report).await?;
     }
 
     Ok(())
 }
 
-async fn process_video(id: &str, opts: &Options) -> error::Result<()> {
+async fn process_video(
+    id: &str,
+    title: Option<&str>,
+    listing: Listing<'_>,
+    episode: Option<usize>,
+    opts: &Options,
+    report: &mut report::Report,
+) -> error::Result<()> {
+    let mut video = report::VideoReport::new(id);
+    let started = Instant::now();
+    let requests = opts.session.requests();
+
+    let res = fetch_and_write(id, title, listing, episode, opts, &mut video).await;
+
+    video.duration = started.elapsed().as_secs_f64();
+    video.requests = opts.session.requests() - requests;
+    if let Err(err) = res.as_ref() {
+        video.error = Some(err.to_string());
+    }
+    report.videos.push(video);
+
+    res
+}
+
+/// Fetches comments of the video and writes them, unless the video is skipped
+async fn fetch_and_write(
+    id: &str,
+    title: Option<&str>,
+    listing: Listing<'_>,
+    episode: Option<usize>,
+    opts: &Options,
+    report: &mut report::VideoReport,
+) -> error::Result<()> {
+    // the ledger is keyed by the ID and the timespan, so it is checked before any request
+    if let Some(archive) = opts.archive.as_ref() {
+        if archive.contains(id, &opts.timespan) {
+            return skip(id, "recorded in the archive", opts, report);
+        }
+    }
+
+    // a database holds all videos, so its existence does not mean the video was downloaded
+    let skip_existing = opts.skip_existing && !opts.format.is_database() && !opts.to_stdout();
+
+    // The file name is known in advance only for videos in a channel, a series or search
+    // results, whose titles are listed, and only if the timespan does not depend on the posted
+    // date or the current time. Otherwise the file is checked after get_info below.
+    if let (true, Some(title), Some(wayback)) =
+        (skip_existing, title, opts.timespan.absolute_wayback())
+    {
+        let dest = opts.dest(&template::Vars {
+            id,
+            title,
+            channel: listing.channel,
+            series: listing.series,
+            episode,
+            registered_at: None,
+            wayback: &wayback,
+            interval: opts.timespan.interval().map(|i| i.source()),
+            ext: opts.format.ext(),
+        });
+        if dest.is_some_and(|d| d.exists()) {
+            return skip(id, "already downloaded", opts, report);
+        }
+    }
+
     let info = opts.session.get_info(id).await?;
 
     let wayback = opts.timespan.wayback(info.video.registered_at);
+    report.title = Some(info.video.title.clone());
+    report.wayback = Some(wayback.clone());
+
+    let dest = opts
+        .dest(&template::Vars {
+            id,
+            title: &info.video.title,
+            channel: listing.channel,
+            series: listing.series,
+            episode,
+            registered_at: Some(info.video.registered_at),
+            wayback: &wayback,
+            interval: opts.timespan.interval().map(|i| i.source()),
+            ext: opts.format.ext(),
+        })
+        .ok_or(error::Error::Template)?;
+    if skip_existing && dest.exists() {
+        return skip(id, "already downloaded", opts, report);
+    }
+
+    // rows in a database are upserted, so existing comments are kept without merging
+    let existing =
+        if opts.update && !opts.format.is_database() && !opts.to_stdout() && dest.exists() {
+            Some(opts.format.read(&dest)?)
+        } else {
+            None
+        };
 
     if !opts.quiet {
-        eprintln!("Video: {} ({})", id, info.video.title);
+        match episode {
+            Some(n) => eprintln!("Video: {} ({}, episode {})", id, info.video.title, n),
+            None => eprintln!("Video: {} ({})", id, info.video.title),
+        }
         match wayback {
             nicodo::Wayback::Latest => eprintln!("Latest comments"),
             nicodo::Wayback::DateTime(dt) => {
@@ -134,6 +554,24 @@ async fn process_video(id: &str, opts: &Options) -> error::Result<()> {
         };
     }
 
+    // snapshots up to the newest stored comment hold no new comments
+    let sweep = match existing
+        .as_ref()
+        .and_then(|e| e.iter().map(|c| c.date).max())
+        .and_then(|date| NaiveDateTime::from_timestamp_opt(date as i64, 0))
+    {
+        Some(date) => match wayback.after(date) {
+            Some(sweep) => sweep,
+            None => {
+                if !opts.quiet {
+                    eprintln!("No new comments");
+                }
+                return record(id, opts);
+            }
+        },
+        None => wayback.clone(),
+    };
+
     let progress = if !opts.quiet {
         Some(indicatif::ProgressBar::new(0).with_style(
             indicatif::ProgressStyle::default_bar().template("{wide_bar} {pos}/{len} {msg}"),
@@ -141,9 +579,16 @@ async fn process_video(id: &str, opts: &Options) -> error::Result<()> {
     } else {
         None
     };
+    let snapshots = RefCell::new(vec![]);
     let comments = opts
         .session
-        .get_comments(&info, &wayback, opts.delay, |ctx| {
+        .get_comments(&info, &sweep, opts.delay, |ctx| {
+            snapshots.borrow_mut().push(report::SnapshotReport {
+                wayback: ctx
+                    .wayback
+                    .map(|dt| dt.format(DISPLAY_DATETIME_FORMAT).to_string()),
+                comments: ctx.comments.len(),
+            });
             if let Some(p) = progress.as_ref() {
                 if let Some(dt) = ctx.wayback {
                     p.set_length(ctx.total.try_into().unwrap());
@@ -159,24 +604,249 @@ async fn process_video(id: &str, opts: &Options) -> error::Result<()> {
             }
         })
         .await?;
+    // the last snapshot is taken when the fetch finishes
+    let fetched_at = Local::now();
 
     if let Some(p) = progress.as_ref() {
         p.finish_and_clear();
     }
 
+    report.snapshots = snapshots.into_inner();
+
     let comments_len = comments.len();
+    report.comments = comments_len;
     if comments_len == 0 {
         if !opts.quiet {
             eprintln!("No comments fetched");
         }
-        return Ok(());
+        return record(id, opts);
+    }
+
+    let comments = match opts.ng_filter.as_ref() {
+        Some(filter) => {
+            let (comments, removed) = filter.apply(comments);
+            report.removed = removed;
+            if !opts.quiet && removed.total() > 0 {
+                eprintln!("Removed {} comments by NG rules", removed.total());
+            }
+            comments
+        }
+        None => comments,
+    };
+
+    let range = nicodo::CommentRange {
+        vpos_from: opts.from.map(|t| t.vpos()),
+        vpos_to: opts.to.map(|t| t.vpos()),
+        date_from: opts
+            .since
+            .as_ref()
+            .map(|d| nicodo::jst_timestamp(d.datetime_in_jst(info.video.registered_at))),
+        date_to: opts
+            .until
+            .as_ref()
+            .map(|d| nicodo::jst_timestamp(d.datetime_in_jst(info.video.registered_at))),
+    };
+    let len = comments.len();
+    let comments = comments
+        .into_iter()
+        .filter(|c| range.contains(c))
+        .collect::<Vec<_>>();
+    report.out_of_range = len - comments.len();
+    if report.out_of_range > 0 && !opts.quiet {
+        eprintln!("Removed {} comments out of the range", report.out_of_range);
+    }
+    if comments.is_empty() {
+        if !opts.quiet {
+            eprintln!("No comments left");
+        }
+        return record(id, opts);
+    }
+
+    let (comments, new_len) = match existing {
+        Some(existing) => {
+            let (comments, new_len) = nicodo::merge_comments(existing, comments);
+            if new_len == 0 {
+                if !opts.quiet {
+                    eprintln!("No new comments");
+                }
+                return record(id, opts);
+            }
+            (comments, Some(new_len))
+        }
+        None => (comments, None),
+    };
+    let comments_len = comments.len();
+
+    let to_stdout = opts.to_stdout();
+    let compression = if to_stdout {
+        opts.compress
+    } else {
+        compress::Compression::from_path(&dest)
+    };
+    let filename = if to_stdout {
+        "stdout".to_string()
+    } else {
+        format!(
+            "\"{}\"",
+            dest.file_name().unwrap_or_default().to_string_lossy()
+        )
+    };
+    let format = opts.format.clone();
+    let format_options = opts.format_options.clone();
+    let output = if to_stdout {
+        "-".to_string()
+    } else {
+        dest.to_string_lossy().into_owned()
+    };
+
+    // the info and the destination are kept for the files saved next to the comments
+    let info = spawn_blocking({
+        let dest = dest.clone();
+        move || -> crate::error::Result<nicodo::Info> {
+            if !to_stdout {
+                if let Some(parent) = dest.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+            }
+            #[cfg(feature = "sqlite")]
+            if let Format::SQLite = format {
+                nicodo::write_sqlite(&dest, &info.video, &comments)?;
+                return Ok(info);
+            }
+            let writer = BufWriter::new(if to_stdout {
+                Box::new(std::io::stdout()) as Box<dyn Write>
+            } else {
+                Box::new(std::fs::File::create(&dest)?)
+            });
+            let mut writer = compress::Encoder::new(writer, compression)?;
+            #[cfg(feature = "parquet")]
+            if let Format::Parquet = format {
+                nicodo::write_parquet(&mut writer, &info.video, &comments)?;
+                writer.finish()?;
+                return Ok(info);
+            }
+            let envelope = nicodo::Envelope {
+                video: &info.video,
+                wayback: &wayback,
+                fetched_at,
+            };
+            format.write(&mut writer, &comments, &format_options, Some(&envelope))?;
+            writer.finish()?;
+            Ok(info)
+        }
+    })
+    .await
+    .map_err(|e| error::Error::Error(Box::new(e)))??;
+
+    report.output = Some(output);
+
+    record(id, opts)?;
+
+    if !opts.quiet {
+        eprintln!(
+            "Writing {} comments{} to {}",
+            comments_len,
+            new_len.map(|n| format!(" ({} new)", n)).unwrap_or_default(),
+            filename
+        );
+    }
+
+    if opts.write_info {
+        let path = opts.sidecar(&dest, id, "info.json");
+        write_file(&path, serde_json::to_vec_pretty(&info.metadata())?).await?;
+        if !opts.quiet {
+            eprintln!(
+                "Writing video info to \"{}\"",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            );
+        }
+    }
+
+    // the comments are already saved, so a missing thumbnail does not fail the video
+    if opts.write_thumbnail {
+        if let Err(err) = write_thumbnail(id, &dest, &info, opts).await {
+            let warning = format!("failed to save the thumbnail: {}", err);
+            if !opts.quiet {
+                eprintln!("Warning: {}", warning);
+            }
+            report.warnings.push(warning);
+        }
+    }
+
+    Ok(())
+}
+
+async fn write_thumbnail(
+    id: &str,
+    dest: &Path,
+    info: &nicodo::Info,
+    opts: &Options,
+) -> error::Result<()> {
+    match opts.session.get_thumbnail(&info.video).await? {
+        Some(thumbnail) => {
+            let path = opts.sidecar(dest, id, thumbnail.ext);
+            write_file(&path, thumbnail.data).await?;
+            if !opts.quiet {
+                eprintln!(
+                    "Writing thumbnail to \"{}\"",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+            }
+        }
+        None if !opts.quiet => eprintln!("No thumbnail found"),
+        None => {}
+    }
+    Ok(())
+}
+
+async fn write_file(path: &Path, data: Vec<u8>) -> error::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, data).await?;
+    Ok(())
+}
+
+/// Replaces the trailing extensions of `path` in `exts`, such as "xml" and "gz", with `ext`. Other
+/// dots are part of the name: "Ep.3 final" becomes "Ep.3 final.info.json".
+fn replace_ext(path: &Path, exts: &[&str], ext: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = exts.iter().rev().fold(name.as_ref(), |name, e| {
+        name.strip_suffix(e)
+            .and_then(|n| n.strip_suffix('.'))
+            .unwrap_or(name)
+    });
+    path.with_file_name(format!("{}.{}", name, ext))
+}
+
+/// Records the video in the archive. Videos without comments to write are recorded as well, so
+/// that they are not fetched again on every run.
+fn record(id: &str, opts: &Options) -> error::Result<()> {
+    if let Some(archive) = opts.archive.as_ref() {
+        archive.record(id, &opts.timespan)?;
+    }
+    Ok(())
+}
+
+fn skip(
+    id: &str,
+    reason: &str,
+    opts: &Options,
+    report: &mut report::VideoReport,
+) -> error::Result<()> {
+    if !opts.quiet {
+        eprintln!("Video: {} (skipped: {})", id, reason);
     }
+    report.skipped = true;
+    Ok(())
+}
 
-    let filename = format!(
+fn filename(title: &str, wayback: &nicodo::Wayback, format: &Format) -> String {
+    format!(
         "{}{}.{}",
-        info.video.title,
-        match wayback {
-            nicodo::Wayback::DateTime(dt) => format!("_{}", dt.format(DISPLAY_DATETIME_FORMAT)),
+        template::sanitize(title),
+        match *wayback {
+            nicodo::Wayback::DateTime(dt) => format!("_{}", dt.format(FILENAME_DATETIME_FORMAT)),
             nicodo::Wayback::Period {
                 start,
                 end,
@@ -192,30 +862,29 @@ async fn process_video(id: &str, opts: &Options) -> error::Result<()> {
             ),
             _ => "".to_string(),
         },
-        opts.format.ext(),
-    );
-    let dest = Path::new(&opts.output).join(&filename);
-    let format = opts.format.clone();
-
-    spawn_blocking(move || -> crate::error::Result<()> {
-        let mut file = std::fs::File::create(&dest)?;
-        match format {
-            Format::JSON => {
-                nicodo::write_json(&mut file, &comments)?;
-            }
-            Format::XML => {
-                nicodo::write_xml(&mut file, &comments)?;
-            }
-        }
+        format.ext(),
+    )
+}
 
-        Ok(())
-    })
-    .await
-    .map_err(|e| error::Error::Error(Box::new(e)))??;
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    if !opts.quiet {
-        eprintln!("Writing {} comments to \"{}\"", comments_len, filename);
+    #[test]
+    fn test_replace_ext() {
+        let path = |p: &str, exts: &[&str]| replace_ext(Path::new(p), exts, "info.json");
+        assert_eq!(
+            path("a/title_latest.xml.gz", &["xml", "gz"]),
+            Path::new("a/title_latest.info.json")
+        );
+        assert_eq!(
+            path("Ep.3 final.xml", &["xml"]),
+            Path::new("Ep.3 final.info.json")
+        );
+        assert_eq!(
+            path("Ep.3 final", &["xml"]),
+            Path::new("Ep.3 final.info.json")
+        );
+        assert_eq!(path("a.json", &["xml"]), Path::new("a.json.info.json"));
     }
-
-    Ok(())
 }
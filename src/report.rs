@@ -0,0 +1,90 @@
+use chrono::Local;
+use serde::Serialize;
+use std::{path::Path, time::Instant};
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub started_at: String,
+    pub duration: f64,
+    pub videos: Vec<VideoReport>,
+    /// Total number of comments removed by NG rules
+    pub removed: nicodo::NgCount,
+    pub error: Option<String>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VideoReport {
+    pub id: String,
+    pub title: Option<String>,
+    pub wayback: Option<nicodo::Wayback>,
+    /// Number of HTTP requests sent for the video, including the watch page and the keys
+    pub requests: usize,
+    pub snapshots: Vec<SnapshotReport>,
+    pub comments: usize,
+    /// Number of fetched comments removed by NG rules
+    pub removed: nicodo::NgCount,
+    /// Number of fetched comments removed by the playback time and date range
+    pub out_of_range: usize,
+    pub output: Option<String>,
+    pub duration: f64,
+    pub skipped: bool,
+    /// Failures which did not stop the video, such as a missing thumbnail
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotReport {
+    /// Wayback datetime, or null for latest comments
+    pub wayback: Option<String>,
+    pub comments: usize,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Self {
+            started_at: Local::now().to_rfc3339(),
+            duration: 0.0,
+            videos: vec![],
+            removed: Default::default(),
+            error: None,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn finish<E: ToString>(&mut self, error: Option<E>) {
+        self.duration = self.started.elapsed().as_secs_f64();
+        for v in &self.videos {
+            self.removed.add(&v.removed);
+        }
+        self.error = error.map(|e| e.to_string());
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> crate::error::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+impl VideoReport {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: None,
+            wayback: None,
+            requests: 0,
+            snapshots: vec![],
+            comments: 0,
+            removed: Default::default(),
+            out_of_range: 0,
+            output: None,
+            duration: 0.0,
+            skipped: false,
+            warnings: vec![],
+            error: None,
+        }
+    }
+}
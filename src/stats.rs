@@ -0,0 +1,149 @@
+use crate::{
+    compress, datetime,
+    error::{Error, Result},
+    id::Id,
+};
+use clap::Parser;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Width of the longest bar of the histogram
+const BAR_WIDTH: usize = 40;
+/// Longest playback counted for a comment file, in seconds
+const MAX_FILE_DURATION: usize = 24 * 3600;
+
+#[derive(Debug, Parser)]
+pub struct Opts {
+    /// Print statistics as JSON
+    #[clap(long)]
+    json: bool,
+    /// Number of entries in rankings
+    #[clap(long, default_value = "10")]
+    top: usize,
+    /// Bucket size of the comment density: 10, 0:30
+    #[clap(long, default_value = "10")]
+    bucket: datetime::PlaybackTime,
+    /// Write the comment density as CSV
+    #[clap(long)]
+    density_csv: Option<String>,
+    /// Write the comment density as an SVG chart
+    #[clap(long)]
+    density_svg: Option<String>,
+    /// Video ID, video URL or comment file (XML or JSON, optionally compressed)
+    input: String,
+}
+
+impl Opts {
+    /// Comment files can be read without signing in. Input which looks like a path is not
+    /// taken for a video ID, so a mistyped file name is reported as missing.
+    pub fn is_file(&self) -> Result<bool> {
+        if Path::new(&self.input).is_file() {
+            return Ok(true);
+        }
+        if !self.input.starts_with("https://") && self.input.contains(['/', '\\', '.']) {
+            return Err(Error::FileNotFound(self.input.clone()));
+        }
+        Ok(false)
+    }
+}
+
+/// Shows statistics of a comment file, or of the latest comments of a video with the session.
+pub async fn stats(opts: &Opts, session: Option<&nicodo::Session>) -> Result<()> {
+    if opts.bucket.vpos() <= 0 {
+        return Err(Error::Bucket);
+    }
+
+    // the length of a video in a file is unknown, so the density ends at the last comment, and a
+    // hostile vpos is capped not to allocate buckets without bound
+    let (comments, duration) = match session {
+        None => {
+            let comments = nicodo::read_comments(compress::open(&opts.input)?)?;
+            let duration = comments.iter().map(|c| c.vpos).max().unwrap_or_default() / 100 + 1;
+            (comments, (duration.max(0) as usize).min(MAX_FILE_DURATION))
+        }
+        Some(session) => {
+            let id = match opts.input.parse::<Id>().map_err(|_| Error::StatsInput)? {
+                Id::Video(id) => id,
+                Id::Channel(_) | Id::Series(_) => return Err(Error::StatsInput),
+            };
+            let info = session.get_info(&id).await?;
+            eprintln!("Video: {} ({})", id, info.video.title);
+            let comments = session
+                .get_comments(&info, &nicodo::Wayback::Latest, None, |_| {})
+                .await?;
+            (comments, info.video.duration)
+        }
+    };
+
+    let density = nicodo::Density::new(&comments, duration, opts.bucket.vpos())?;
+    if let Some(path) = opts.density_csv.as_ref() {
+        let mut writer = BufWriter::new(File::create(path)?);
+        nicodo::write_density_csv(&mut writer, &density)?;
+        writer.flush()?;
+        eprintln!("Writing comment density to \"{}\"", path);
+    }
+    if let Some(path) = opts.density_svg.as_ref() {
+        let mut writer = BufWriter::new(File::create(path)?);
+        nicodo::write_density_svg(&mut writer, &density, &Default::default())?;
+        writer.flush()?;
+        eprintln!("Writing comment density chart to \"{}\"", path);
+    }
+
+    let stats = nicodo::stats(
+        &comments,
+        &nicodo::StatsOptions {
+            top: opts.top,
+            duration: Some(duration),
+        },
+    );
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print_table(&stats);
+    }
+
+    Ok(())
+}
+
+fn print_table(stats: &nicodo::Stats) {
+    println!("Comments: {}", stats.comments);
+
+    println!("\nPer minute:");
+    let max = stats.per_minute.iter().copied().max().unwrap_or_default();
+    for (minute, count) in stats.per_minute.iter().enumerate() {
+        println!(
+            "{:>8} {:>7} {}",
+            nicodo::playback_time(minute as isize * 6000),
+            count,
+            "#".repeat(count * BAR_WIDTH / max.max(1))
+        );
+    }
+
+    println!("\nPer day (JST):");
+    for (day, count) in &stats.per_day {
+        println!("{:>10} {:>7}", day, count);
+    }
+
+    for (name, entries) in [
+        ("Top users", &stats.top_users),
+        ("Frequent comments", &stats.frequent_comments),
+        ("Commands", &stats.commands),
+    ] {
+        println!("\n{}:", name);
+        for (key, count) in entries {
+            println!("{:>7} {}", count, key);
+        }
+    }
+
+    println!("\nPeaks:");
+    for peak in &stats.peaks {
+        println!(
+            "{:>8} {:>7}",
+            nicodo::playback_time(peak.vpos),
+            peak.comments
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::NaiveDateTime;
+use std::{path::PathBuf, str::FromStr};
+
+const DATETIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Output path template: {series}/{episode}_{title}_{start}.{ext}
+#[derive(Debug, Clone)]
+pub struct Template(Vec<Vec<Part>>);
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Id,
+    Title,
+    Channel,
+    Series,
+    Episode,
+    RegisteredAt,
+    Start,
+    End,
+    Interval,
+    Ext,
+}
+
+/// Values substituted for the placeholders
+#[derive(Debug)]
+pub struct Vars<'a> {
+    pub id: &'a str,
+    pub title: &'a str,
+    pub channel: Option<&'a str>,
+    pub series: Option<&'a str>,
+    /// Number of the video in a series
+    pub episode: Option<usize>,
+    /// Unknown until the video info is fetched
+    pub registered_at: Option<NaiveDateTime>,
+    pub wayback: &'a nicodo::Wayback,
+    pub interval: Option<&'a str>,
+    pub ext: &'a str,
+}
+
+impl FromStr for Template {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        const ERR: &str = "invalid placeholder in the template";
+
+        let components = s
+            .split('/')
+            .map(|c| {
+                let mut parts = vec![];
+                let mut rest = c;
+                while let Some(i) = rest.find('{') {
+                    if i > 0 {
+                        parts.push(Part::Text(rest[..i].to_string()));
+                    }
+                    let j = rest[i..].find('}').ok_or(ERR)? + i;
+                    parts.push(match &rest[i + 1..j] {
+                        "id" => Part::Id,
+                        "title" => Part::Title,
+                        "channel" => Part::Channel,
+                        "series" => Part::Series,
+                        "episode" => Part::Episode,
+                        "registered_at" => Part::RegisteredAt,
+                        "start" => Part::Start,
+                        "end" => Part::End,
+                        "interval" => Part::Interval,
+                        "ext" => Part::Ext,
+                        _ => return Err(ERR),
+                    });
+                    rest = &rest[j + 1..];
+                }
+                if !rest.is_empty() {
+                    parts.push(Part::Text(rest.to_string()));
+                }
+                Ok(parts)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        if components.last().is_some_and(|c| c.is_empty()) {
+            return Err("the template has no file name");
+        }
+
+        Ok(Self(components))
+    }
+}
+
+impl Template {
+    /// Renders a relative path. Each value is sanitized so that it cannot add a directory, and
+    /// directories which end up empty, such as `{channel}` of a single video, are omitted.
+    /// Returns `None` if a value used in the template is unknown yet.
+    pub fn render(&self, vars: &Vars) -> Option<PathBuf> {
+        let format = |dt: &NaiveDateTime| dt.format(DATETIME_FORMAT).to_string();
+        let (start, end) = match vars.wayback {
+            nicodo::Wayback::Latest => ("latest".to_string(), "latest".to_string()),
+            nicodo::Wayback::DateTime(dt) => (format(dt), format(dt)),
+            nicodo::Wayback::Period { start, end, .. } => (format(start), format(end)),
+        };
+
+        let mut path = PathBuf::new();
+        for component in &self.0 {
+            let mut s = String::new();
+            for part in component {
+                s.push_str(&match part {
+                    Part::Text(t) => t.clone(),
+                    Part::Id => sanitize(vars.id),
+                    Part::Title => sanitize(vars.title),
+                    Part::Channel => sanitize(vars.channel.unwrap_or_default()),
+                    Part::Series => sanitize(vars.series.unwrap_or_default()),
+                    Part::Episode => vars.episode.map(|n| n.to_string()).unwrap_or_default(),
+                    Part::RegisteredAt => format(&vars.registered_at?),
+                    Part::Start => start.clone(),
+                    Part::End => end.clone(),
+                    Part::Interval => sanitize(vars.interval.unwrap_or_default()),
+                    Part::Ext => vars.ext.to_string(),
+                });
+            }
+            match s.trim_end_matches(['.', ' ']) {
+                "" => {}
+                s => path.push(s),
+            }
+        }
+        Some(path)
+    }
+}
+
+/// Replaces characters which cannot be used in file names on Windows, macOS or Linux. Trailing
+/// dots and spaces, which Windows drops, are removed, and reserved device names such as `CON` and
+/// `nul.txt` get `_` after the name.
+pub fn sanitize(s: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let s = s
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let s = s.trim_end_matches(['.', ' ']);
+
+    let (name, rest) = s.split_at(s.find('.').unwrap_or(s.len()));
+    if RESERVED
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(name.trim_end()))
+    {
+        format!("{}_{}", name, rest)
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::path::Path;
+
+    fn vars<'a>(wayback: &'a nicodo::Wayback, channel: Option<&'a str>) -> Vars<'a> {
+        Vars {
+            id: "sm9",
+            title: "a/b: c?",
+            channel,
+            series: Some("series1"),
+            episode: Some(3),
+            registered_at: Some(NaiveDate::from_ymd(2007, 3, 6).and_hms(0, 33, 0)),
+            wayback,
+            interval: Some("1d"),
+            ext: "xml",
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let period = nicodo::Wayback::Period {
+            start: NaiveDate::from_ymd(2007, 3, 6).and_hms(0, 0, 0),
+            end: NaiveDate::from_ymd(2007, 3, 8).and_hms(0, 0, 0),
+            interval: chrono::Duration::days(1),
+            include_latest: false,
+        };
+        let t = "{channel}/{registered_at}_{id}_{title}_{start}-{end}_{interval}.{ext}"
+            .parse::<Template>()
+            .unwrap();
+        assert_eq!(
+            t.render(&vars(&period, Some("ch1"))).unwrap(),
+            Path::new("ch1/20070306003300_sm9_a_b_ c__20070306000000-20070308000000_1d.xml")
+        );
+        let t = "{series}/{episode}_{title}.{ext}"
+            .parse::<Template>()
+            .unwrap();
+        assert_eq!(
+            t.render(&vars(&period, None)).unwrap(),
+            Path::new("series1/3_a_b_ c_.xml")
+        );
+
+        let t = "{channel}/{title}_{start}.{ext}"
+            .parse::<Template>()
+            .unwrap();
+        assert_eq!(
+            t.render(&vars(&nicodo::Wayback::Latest, None)).unwrap(),
+            Path::new("a_b_ c__latest.xml")
+        );
+
+        let mut v = vars(&nicodo::Wayback::Latest, None);
+        v.registered_at = None;
+        assert!(t.render(&v).is_some());
+        let t = "{registered_at}.{ext}".parse::<Template>().unwrap();
+        assert!(t.render(&v).is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!("{title}.{ext}".parse::<Template>().is_ok());
+        assert!("{unknown}.{ext}".parse::<Template>().is_err());
+        assert!("{title.{ext}".parse::<Template>().is_err());
+        assert!("{title}/".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(
+            sanitize("a/b\\c:d*e?f\"g<h>i|j\nk"),
+            "a_b_c_d_e_f_g_h_i_j_k"
+        );
+        assert_eq!(sanitize("ｆｕｌｌ／width"), "ｆｕｌｌ／width");
+        assert_eq!(sanitize("title. . "), "title");
+        assert_eq!(sanitize("CON"), "CON_");
+        assert_eq!(sanitize("nul.txt"), "nul_.txt");
+        assert_eq!(sanitize("com1 "), "com1_");
+        assert_eq!(sanitize("CONSOLE"), "CONSOLE");
+    }
+}